@@ -0,0 +1,81 @@
+use {
+  clap::Parser,
+  degenerate::{Cpu, Filter, Message},
+  serde::Deserialize,
+  std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+  },
+};
+
+#[derive(Parser)]
+#[clap(about = "Render a JSON array of filters to a PNG")]
+struct Arguments {
+  #[clap(help = "Read filters from <INPUT>, or from standard input if omitted")]
+  input: Option<PathBuf>,
+  #[clap(long, default_value = "degenerate.png", help = "Write PNG to <OUTPUT>")]
+  output: PathBuf,
+  #[clap(
+    long,
+    default_value = "1024",
+    help = "Render at <RESOLUTION>x<RESOLUTION> pixels, unless overridden by --width or --height"
+  )]
+  resolution: u32,
+  #[clap(long, help = "Render <WIDTH> pixels wide")]
+  width: Option<u32>,
+  #[clap(long, help = "Render <HEIGHT> pixels high")]
+  height: Option<u32>,
+}
+
+// Inputs are filters, or the `render`, `clear`, and `present` messages that
+// scripts send to the renderer. Messages are tried first, since any object
+// deserializes as a filter.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Input {
+  Message(Message),
+  Filter(Filter),
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+  let arguments = Arguments::parse();
+
+  let json = match arguments.input {
+    Some(path) => fs::read_to_string(path)?,
+    None => {
+      let mut json = String::new();
+      io::stdin().read_to_string(&mut json)?;
+      json
+    }
+  };
+
+  let inputs = serde_json::from_str::<Vec<Input>>(&json)?;
+
+  let mut cpu = Cpu::with_dimensions(
+    arguments.width.unwrap_or(arguments.resolution),
+    arguments.height.unwrap_or(arguments.resolution),
+  );
+
+  for input in &inputs {
+    match input {
+      Input::Filter(filter) => cpu.render(filter)?,
+      Input::Message(Message::Render(filter)) => cpu.render(filter)?,
+      Input::Message(Message::Clear) => cpu.clear(),
+      Input::Message(Message::Present(name)) => cpu.present(name),
+      Input::Message(message) => {
+        return Err(
+          format!(
+            "Message cannot be rendered without a browser: {}",
+            serde_json::to_string(message)?
+          )
+          .into(),
+        )
+      }
+    }
+  }
+
+  cpu.into_image().save(&arguments.output)?;
+
+  Ok(())
+}
@@ -0,0 +1,12 @@
+check();
+render();
+reset();
+blend('difference');
+rotateColor('red', 0.25 * TAU);
+circle(0.75);
+render();
+reset();
+blend('xor');
+rotateColor('blue', 0.25 * TAU);
+x();
+render();
@@ -0,0 +1,11 @@
+destination('mask');
+circle(0.5);
+render();
+destination('background');
+check();
+render();
+reset();
+source('background');
+mask('mask');
+rotateColor('green', 0.5 * TAU);
+render();
@@ -0,0 +1,8 @@
+check();
+rotateColor('green', 0.5 * TAU);
+render();
+reset();
+colorSpace('hsv');
+rotateHue(TAU / 3);
+circle(0.75);
+render();
@@ -0,0 +1,6 @@
+customField(`
+  float f(vec2 p) {
+    return length(p) - 0.5;
+  }
+`);
+render();
@@ -0,0 +1,6 @@
+circle(0.5);
+render();
+square(1.5, 0.25);
+render();
+x(1.0, 0.1);
+render();
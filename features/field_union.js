@@ -0,0 +1,5 @@
+union(circle(0.5), transformField(cross(), TAU / 8));
+render();
+subtract(square(1.5), circle(0.5));
+rotateColor('green', 0.5 * TAU);
+render();
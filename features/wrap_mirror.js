@@ -0,0 +1,8 @@
+x();
+rotateColor('green', 0.5 * TAU);
+render();
+reset();
+wrap('mirror');
+transform(0, [3.0, 3.0], [0.3, 0.1]);
+all();
+render();
@@ -1,5 +1,13 @@
 use degenerate::*;
 
+fn _save(frame: Frame) {
+  Filter::new().x().render();
+  if frame.number == 60 {
+    save();
+    done();
+  }
+}
+
 fn _fade_in(frame: Frame) {
   Filter::new()
     .x()
@@ -53,6 +61,21 @@ fn _orbs() {
     .render();
 }
 
+fn _widgets() {
+  let filter = if checkbox("circle") {
+    Filter::new().circle()
+  } else {
+    Filter::new().x()
+  };
+
+  let scale = slider("scale", 0.1, 4.0, 0.1, 2.0) as f32;
+
+  filter
+    .position(Similarity2::from_scaling(scale))
+    .wrap(radio("wrap", &["off", "on"]) == "on")
+    .render();
+}
+
 fn _x() {
   for i in 0..8 {
     Filter::new()
@@ -0,0 +1,193 @@
+use super::*;
+
+// Number of frames of frequency data kept in the spectrogram
+const SPECTROGRAM_ROWS: u32 = 256;
+
+// Audio data read from an `AnalyserNode`, and the textures that fields sample
+// it from. Textures are sized from the node's FFT size when created, so a new
+// `Analyser` must be created when the FFT size changes.
+pub(crate) struct Analyser {
+  decibels: Vec<f32>,
+  frequency_array: Float32Array,
+  frequency_data: Vec<f32>,
+  frequency_texture: WebGlTexture,
+  node: AnalyserNode,
+  spectrogram_row: u32,
+  spectrogram_texture: WebGlTexture,
+  time_domain_array: Float32Array,
+  time_domain_data: Vec<f32>,
+  time_domain_texture: WebGlTexture,
+}
+
+impl Analyser {
+  pub(crate) fn new(gl: &WebGl2RenderingContext, node: &AnalyserNode) -> Result<Self> {
+    let fft_size = node.fft_size();
+    let frequency_bin_count = node.frequency_bin_count();
+
+    Ok(Self {
+      decibels: vec![0.0; frequency_bin_count as usize],
+      frequency_array: Float32Array::new_with_length(frequency_bin_count),
+      frequency_data: vec![0.0; frequency_bin_count as usize],
+      frequency_texture: Self::create_texture(gl, frequency_bin_count, 1)?,
+      node: node.clone(),
+      spectrogram_row: 0,
+      spectrogram_texture: Self::create_texture(gl, frequency_bin_count, SPECTROGRAM_ROWS)?,
+      time_domain_array: Float32Array::new_with_length(fft_size),
+      time_domain_data: vec![0.0; fft_size as usize],
+      time_domain_texture: Self::create_texture(gl, fft_size, 1)?,
+    })
+  }
+
+  fn create_texture(gl: &WebGl2RenderingContext, width: u32, height: u32) -> Result<WebGlTexture> {
+    let texture = gl
+      .create_texture()
+      .ok_or("Failed to create audio texture")?;
+
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+    gl.tex_parameteri(
+      WebGl2RenderingContext::TEXTURE_2D,
+      WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+      WebGl2RenderingContext::NEAREST.try_into()?,
+    );
+
+    gl.tex_parameteri(
+      WebGl2RenderingContext::TEXTURE_2D,
+      WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+      WebGl2RenderingContext::NEAREST.try_into()?,
+    );
+
+    gl.tex_storage_2d(
+      WebGl2RenderingContext::TEXTURE_2D,
+      1,
+      WebGl2RenderingContext::R32F,
+      width.try_into()?,
+      height.try_into()?,
+    );
+
+    Ok(texture)
+  }
+
+  // Frequency data in decibels, as read before normalization
+  pub(crate) fn decibels(&self) -> &[f32] {
+    &self.decibels
+  }
+
+  pub(crate) fn frequency_data(&self) -> &[f32] {
+    &self.frequency_data
+  }
+
+  pub(crate) fn time_domain_data(&self) -> &[f32] {
+    &self.time_domain_data
+  }
+
+  pub(crate) fn spectrogram_row(&self) -> u32 {
+    self.spectrogram_row
+  }
+
+  // Read time domain and frequency data from `offline`, if given, otherwise
+  // from the node, and normalize frequency data to [0, 1] over the decibel
+  // range
+  pub(crate) fn read(
+    &mut self,
+    offline: Option<&(Vec<f32>, Vec<f32>)>,
+    decibels_min: f32,
+    decibels_max: f32,
+  ) {
+    match offline {
+      Some((time_domain, frequency)) => {
+        self.time_domain_data.copy_from_slice(time_domain);
+        self.frequency_data.copy_from_slice(frequency);
+      }
+      None => {
+        self
+          .node
+          .get_float_time_domain_data(&mut self.time_domain_data);
+        self.node.get_float_frequency_data(&mut self.frequency_data);
+      }
+    }
+
+    self.decibels.copy_from_slice(&self.frequency_data);
+
+    let scale_factor = 1.0 / (decibels_max - decibels_min);
+
+    for bucket in &mut self.frequency_data {
+      *bucket = ((*bucket - decibels_min) * scale_factor).clamp(0.0, 1.0);
+    }
+  }
+
+  // Replace time domain and frequency data with data previously read, and
+  // already normalized, by `read`
+  pub(crate) fn restore(&mut self, time_domain: &[f32], frequency: &[f32]) -> Result {
+    if time_domain.len() != self.time_domain_data.len()
+      || frequency.len() != self.frequency_data.len()
+    {
+      return Err("Audio data was read with a different FFT size".into());
+    }
+
+    self.time_domain_data.copy_from_slice(time_domain);
+    self.frequency_data.copy_from_slice(frequency);
+
+    Ok(())
+  }
+
+  // Upload time domain and frequency data, and bind the textures to the units
+  // that `fragment.glsl` samples them from
+  pub(crate) fn bind(&self, gl: &WebGl2RenderingContext) -> Result {
+    self.time_domain_array.copy_from(&self.time_domain_data);
+    gl.active_texture(WebGl2RenderingContext::TEXTURE1);
+    gl.bind_texture(
+      WebGl2RenderingContext::TEXTURE_2D,
+      Some(&self.time_domain_texture),
+    );
+    Self::upload_row(gl, 0, &self.time_domain_array)?;
+
+    self.frequency_array.copy_from(&self.frequency_data);
+    gl.active_texture(WebGl2RenderingContext::TEXTURE2);
+    gl.bind_texture(
+      WebGl2RenderingContext::TEXTURE_2D,
+      Some(&self.frequency_texture),
+    );
+    Self::upload_row(gl, 0, &self.frequency_array)?;
+
+    gl.active_texture(WebGl2RenderingContext::TEXTURE5);
+    gl.bind_texture(
+      WebGl2RenderingContext::TEXTURE_2D,
+      Some(&self.spectrogram_texture),
+    );
+
+    Ok(())
+  }
+
+  // Write the current frequency data into the spectrogram, replacing the
+  // oldest row
+  pub(crate) fn push_spectrogram(&mut self, gl: &WebGl2RenderingContext) -> Result {
+    self.frequency_array.copy_from(&self.frequency_data);
+    gl.active_texture(WebGl2RenderingContext::TEXTURE5);
+    gl.bind_texture(
+      WebGl2RenderingContext::TEXTURE_2D,
+      Some(&self.spectrogram_texture),
+    );
+    Self::upload_row(gl, self.spectrogram_row, &self.frequency_array)?;
+
+    self.spectrogram_row = (self.spectrogram_row + 1) % SPECTROGRAM_ROWS;
+
+    Ok(())
+  }
+
+  fn upload_row(gl: &WebGl2RenderingContext, row: u32, array: &Float32Array) -> Result {
+    gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+      WebGl2RenderingContext::TEXTURE_2D,
+      0,
+      0,
+      row.try_into()?,
+      array.length().try_into()?,
+      1,
+      WebGl2RenderingContext::RED,
+      WebGl2RenderingContext::FLOAT,
+      Some(array),
+    )?;
+
+    Ok(())
+  }
+}
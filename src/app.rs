@@ -1,19 +1,33 @@
 use super::*;
 
 pub(crate) struct App {
-  analyser_node: AnalyserNode,
+  analyser_nodes: [AnalyserNode; 3],
   animation_frame_callback: Option<Closure<dyn FnMut(f64)>>,
   aside: HtmlElement,
+  audio: Option<Audio>,
+  audio_bins: bool,
+  audio_loads: u64,
   audio_context: AudioContext,
+  audio_input_node: GainNode,
+  beat_detector: BeatDetector,
+  camera: Option<Camera>,
+  camera_requests: u64,
+  capture: Option<Capture>,
   document: Document,
+  frame: u64,
   gpu: Gpu,
   html: HtmlElement,
+  image_input: HtmlInputElement,
   nav: HtmlElement,
   oscillator_gain_node: GainNode,
   oscillator_node: OscillatorNode,
   recording: bool,
+  replay: Option<Replay>,
   run_button: HtmlButtonElement,
+  script: String,
   select: HtmlSelectElement,
+  session: Option<Session>,
+  session_input: HtmlInputElement,
   share_button: HtmlButtonElement,
   stderr: Stderr,
   textarea: HtmlTextAreaElement,
@@ -60,6 +74,14 @@ impl App {
 
     let share_button = document.select::<HtmlButtonElement>("button#share")?;
 
+    let replay_button = document.select::<HtmlButtonElement>("button#replay")?;
+
+    let session_input = document.select::<HtmlInputElement>("input#session")?;
+
+    let open_button = document.select::<HtmlButtonElement>("button#open")?;
+
+    let image_input = document.select::<HtmlInputElement>("input#image")?;
+
     for name in EXAMPLES.keys() {
       let option = document
         .create_element("option")?
@@ -76,11 +98,36 @@ impl App {
 
     let audio_context = AudioContext::new()?;
 
-    let analyser_node = audio_context.create_analyser()?;
+    // All audio sources connect to the input node, which up-mixes mono sources
+    // to stereo, and feeds the mix analyser, and the left and right analysers
+    // through a channel splitter
+    let audio_input_node = audio_context.create_gain()?;
+    audio_input_node.set_channel_count(2);
+    audio_input_node.set_channel_count_mode(ChannelCountMode::Explicit);
+    audio_input_node.set_channel_interpretation(ChannelInterpretation::Speakers);
+
+    let channel_splitter_node = audio_context.create_channel_splitter_with_number_of_outputs(2)?;
+    audio_input_node.connect_with_audio_node(&channel_splitter_node)?;
+
+    let analyser_nodes = [
+      audio_context.create_analyser()?,
+      audio_context.create_analyser()?,
+      audio_context.create_analyser()?,
+    ];
+
+    for analyser_node in &analyser_nodes {
+      analyser_node.set_smoothing_time_constant(0.5);
+    }
+
+    audio_input_node.connect_with_audio_node(&analyser_nodes[AudioChannel::Mix as usize])?;
 
-    analyser_node.set_smoothing_time_constant(0.5);
+    channel_splitter_node
+      .connect_with_audio_node_and_output(&analyser_nodes[AudioChannel::Left as usize], 0)?;
 
-    let gpu = Gpu::new(&window, &canvas, &analyser_node)?;
+    channel_splitter_node
+      .connect_with_audio_node_and_output(&analyser_nodes[AudioChannel::Right as usize], 1)?;
+
+    let gpu = Gpu::new(&window, &canvas, &analyser_nodes)?;
 
     let location = window.location();
 
@@ -106,22 +153,37 @@ impl App {
 
     oscillator_gain_node.connect_with_audio_node(&audio_context.destination())?;
 
-    oscillator_gain_node.connect_with_audio_node(&analyser_node)?;
+    oscillator_gain_node.connect_with_audio_node(&audio_input_node)?;
 
+    #[allow(clippy::arc_with_non_send_sync)]
     let app = Arc::new(Mutex::new(Self {
-      analyser_node,
+      analyser_nodes,
       animation_frame_callback: None,
       aside: document.select::<HtmlElement>("aside")?,
+      audio: None,
+      audio_bins: false,
+      audio_loads: 0,
       audio_context,
+      audio_input_node,
+      beat_detector: BeatDetector::new(),
+      camera: None,
+      camera_requests: 0,
+      capture: None,
       document,
+      frame: 0,
       gpu,
-      html,
+      html: html.clone(),
+      image_input: image_input.clone(),
       nav,
       oscillator_gain_node,
       oscillator_node,
       recording: false,
+      replay: None,
       run_button: run_button.clone(),
+      script: String::new(),
       select: select.clone(),
+      session: None,
+      session_input: session_input.clone(),
       share_button: share_button.clone(),
       stderr: stderr.clone(),
       textarea: textarea.clone(),
@@ -156,13 +218,38 @@ impl App {
     })?;
 
     Self::add_event_listener_with_event(&app, &worker, "message", move |app, event| {
-      app.on_message(event)
+      app.on_worker_message(event)
     })?;
 
     Self::add_event_listener(&app, &run_button, "click", move |app| app.on_run())?;
 
     Self::add_event_listener(&app, &share_button, "click", move |app| app.on_share())?;
 
+    Self::add_event_listener(&app, &replay_button, "click", move |app| {
+      app.session_input.click();
+      Ok(())
+    })?;
+
+    Self::add_event_listener(&app, &session_input, "change", move |app| {
+      app.on_session_input()
+    })?;
+
+    Self::add_event_listener(&app, &open_button, "click", move |app| {
+      app.image_input.click();
+      Ok(())
+    })?;
+
+    Self::add_event_listener(&app, &image_input, "change", move |app| {
+      app.on_image_input()
+    })?;
+
+    Self::add_event_listener_with_event(&app, &html, "dragover", move |_, event: DragEvent| {
+      event.prevent_default();
+      Ok(())
+    })?;
+
+    Self::add_event_listener_with_event(&app, &html, "drop", move |app, event| app.on_drop(event))?;
+
     let path = location.pathname()?;
 
     match path.split_inclusive('/').collect::<Vec<&str>>().as_slice() {
@@ -236,7 +323,13 @@ impl App {
     Ok(())
   }
 
-  pub(super) fn run_script(&self, script: &str) -> Result {
+  pub(super) fn run_script(&mut self, script: &str) -> Result {
+    self.script = script.into();
+
+    self.stop_audio()?;
+    self.stop_camera()?;
+    self.gpu.clear_images();
+
     self
       .worker
       .post_message(&JsValue::from_str(&serde_json::to_string(&Event::Script(
@@ -250,28 +343,201 @@ impl App {
 
     self.gpu.resize()?;
 
+    if let Some(audio) = &mut self.audio {
+      audio.tick();
+      self.gpu.set_offline_audio(audio.analyze());
+    }
+
+    if let Some(camera) = &mut self.camera {
+      self.gpu.draw_camera(camera)?;
+      self.gpu.present()?;
+    }
+
+    self.frame += 1;
+
+    if let Some(capture) = &mut self.capture {
+      capture.push(self.gpu.save_image()?.to_rgba8());
+
+      if capture.is_done() {
+        let capture = self.capture.take().unwrap();
+        let filename = capture.filename();
+        let media_type = capture.media_type();
+        self.download(filename, media_type, &capture.encode()?)?;
+      }
+    }
+
+    if let Some(replay) = &mut self.replay {
+      let messages = replay.messages(self.frame);
+
+      if replay.is_done() {
+        self.replay = None;
+      }
+
+      for message in messages {
+        self.on_message(message)?;
+      }
+
+      if self.replay.is_none() {
+        self.html.set_class_name("done");
+      }
+
+      return Ok(());
+    }
+
+    self.gpu.analyze()?;
+
+    let audio = self
+      .gpu
+      .audio_summary(self.audio_context.sample_rate(), self.audio_bins);
+
+    // Offline renders follow the track, so that detected beats don't depend
+    // on how long frames take to render
+    let tempo = self.beat_detector.update(
+      self.gpu.audio_frequency_data(),
+      self
+        .audio
+        .as_ref()
+        .and_then(Audio::offline_time)
+        .map_or(timestamp, |time| time * 1000.0),
+    );
+
     self
       .worker
-      .post_message(&JsValue::from_str(&serde_json::to_string(&Event::Frame(
-        timestamp as f32,
-      ))?))?;
+      .post_message(&JsValue::from_str(&serde_json::to_string(&Event::Frame {
+        audio,
+        tempo,
+        time: timestamp as f32,
+      })?))?;
 
     Ok(())
   }
 
-  fn on_message(&mut self, event: MessageEvent) -> Result {
-    let event = serde_json::from_str(
+  fn on_worker_message(&mut self, event: MessageEvent) -> Result {
+    let message = serde_json::from_str(
       &event
         .data()
         .as_string()
         .ok_or("Failed to retrieve event data as a string")?,
     )?;
 
-    match event {
+    if self.replay.is_none() {
+      self.on_message(message)?;
+    }
+
+    Ok(())
+  }
+
+  fn on_message(&mut self, message: Message) -> Result {
+    if let Some(session) = &mut self.session {
+      if !matches!(message, Message::RecordSession | Message::SaveSession) {
+        session.record(self.frame, message.clone());
+      }
+    }
+
+    match message {
+      Message::Analyser {
+        fft_size,
+        smoothing,
+      } => {
+        if !fft_size.is_power_of_two() || !(32..=32768).contains(&fft_size) {
+          return Err(
+            format!("FFT size must be a power of two between 32 and 32768: {fft_size}").into(),
+          );
+        }
+
+        if !(0.0..=1.0).contains(&smoothing) {
+          return Err(format!("Smoothing must be between 0 and 1: {smoothing}").into());
+        }
+
+        for analyser_node in &self.analyser_nodes {
+          analyser_node.set_fft_size(fft_size);
+          analyser_node.set_smoothing_time_constant(smoothing.into());
+        }
+
+        self.gpu.resize_analysers()?;
+      }
+      Message::Aspect(aspect) => {
+        self.gpu.set_aspect(aspect)?;
+      }
+      Message::Audio(audio) => {
+        let array = js_sys::Uint8Array::from(base64::decode(audio)?.as_slice());
+        let load = self.audio_loads;
+        let local = self.this();
+        let closure = Closure::wrap(Box::new(move |buffer: JsValue| {
+          let mut app = local.lock().unwrap();
+          let result = app.on_audio_buffer(load, buffer);
+          app.stderr.update(result);
+        }) as Box<dyn FnMut(JsValue)>);
+        let _ = self
+          .audio_context
+          .decode_audio_data(&array.buffer())?
+          .then(&closure);
+        closure.forget();
+      }
+      Message::AudioBins(enabled) => {
+        self.audio_bins = enabled;
+      }
+      Message::Camera { buffer, placement } => {
+        if let Some(camera) = &mut self.camera {
+          camera.buffer = buffer;
+          camera.placement = placement;
+        } else {
+          self.camera_requests += 1;
+          let request = self.camera_requests;
+          let local = self.this();
+          let closure = Closure::wrap(Box::new(move |stream: JsValue| {
+            let mut app = local.lock().unwrap();
+            let result = app.on_camera_media(request, stream, buffer.clone(), placement);
+            app.stderr.update(result);
+          }) as Box<dyn FnMut(JsValue)>);
+          let _ = self
+            .window
+            .navigator()
+            .media_devices()?
+            .get_user_media_with_constraints(
+              MediaStreamConstraints::new()
+                .audio(&false.into())
+                .video(&true.into()),
+            )?
+            .then(&closure);
+          closure.forget();
+        }
+      }
+      Message::Capture {
+        frames,
+        fps,
+        format,
+      } => {
+        if frames == 0 {
+          return Err("Capture frame count must be greater than zero".into());
+        }
+
+        if fps == 0 {
+          return Err("Capture frame rate must be greater than zero".into());
+        }
+
+        self.capture = Some(Capture::new(frames, fps, format));
+      }
       Message::Clear => {
         self.gpu.clear()?;
       }
       Message::DecibelRange { min, max } => {
+        // Analysers reject ranges where the minimum is not less than the
+        // maximum, so those are only used to normalize frequency data.
+        // Setting a minimum above the current maximum, or a maximum below the
+        // current minimum, also throws, so the order depends on the new range.
+        if min < max {
+          for analyser_node in &self.analyser_nodes {
+            if f64::from(min) < analyser_node.max_decibels() {
+              analyser_node.set_min_decibels(min.into());
+              analyser_node.set_max_decibels(max.into());
+            } else {
+              analyser_node.set_max_decibels(max.into());
+              analyser_node.set_min_decibels(min.into());
+            }
+          }
+        }
+
         self.gpu.set_decibel_range(min, max);
       }
       Message::Done => {
@@ -280,12 +546,43 @@ impl App {
       Message::Error(error) => {
         self.stderr.update(Err(error.into()));
       }
+      Message::LoadImage {
+        image,
+        placement,
+        buffer,
+      } => {
+        let image = image::load_from_memory(&base64::decode(image)?)?.to_rgba8();
+        self
+          .gpu
+          .load_image(&buffer, SourceImage::new(image, placement))?;
+        self.gpu.present()?;
+      }
+      Message::OfflineAudio(fps) => {
+        if matches!(fps, Some(fps) if !fps.is_finite() || fps <= 0.0) {
+          return Err("Offline audio frame rate must be greater than zero".into());
+        }
+
+        self.audio()?.set_offline(fps)?;
+      }
       Message::OscillatorFrequency(frequency) => {
         self.oscillator_node.frequency().set_value(frequency);
       }
       Message::OscillatorGain(gain) => {
         self.oscillator_gain_node.gain().set_value(gain);
       }
+      Message::Pause => {
+        self.audio()?.pause()?;
+      }
+      Message::Play => {
+        self.audio()?.play()?;
+      }
+      Message::Precision(precision) => {
+        self.gpu.set_precision(precision)?;
+      }
+      Message::Present(buffer) => {
+        self.gpu.set_presented(&buffer);
+        self.gpu.present()?;
+      }
       Message::Record => {
         if !self.recording {
           let local = self.this();
@@ -307,26 +604,46 @@ impl App {
           closure.forget();
         }
       }
+      Message::RecordSession => {
+        self.session = Some(Session::new(self.frame));
+      }
+      Message::RecordTiled => {
+        self.gpu.record_tiled();
+      }
       Message::Render(filter) => {
         self.gpu.render(&filter)?;
         self.gpu.present()?;
       }
-      Message::Resolution(resolution) => {
-        self.gpu.lock_resolution(resolution);
+      Message::Resolution { width, height } => {
+        self.gpu.lock_resolution(width, height);
       }
       Message::Save => {
         let image = self.gpu.save_image()?;
-        let mut png = Cursor::new(Vec::new());
-        image.write_to(&mut png, ImageOutputFormat::Png)?;
-        let a = self
-          .document
-          .create_element("a")?
-          .cast::<HtmlAnchorElement>()?;
-        a.set_download("degenerate.png");
-        let mut href = String::from("data:image/png;base64,");
-        base64::encode_config_buf(png.get_ref(), base64::STANDARD, &mut href);
-        a.set_href(&href);
-        a.click();
+        self.save_png(&image)?;
+      }
+      Message::SaveSession => {
+        let session = self
+          .session
+          .as_ref()
+          .ok_or("No session is being recorded")?;
+        self.download(
+          "degenerate-session.json",
+          "application/json",
+          serde_json::to_string(session)?.as_bytes(),
+        )?;
+      }
+      Message::SaveTiled {
+        width,
+        height,
+        tile_size,
+      } => {
+        let image = self
+          .gpu
+          .save_tiled(width, height, tile_size, self.camera.as_mut())?;
+        self.save_png(&image)?;
+      }
+      Message::Seek(time) => {
+        self.audio()?.seek(time)?;
       }
       Message::Widget { name, widget } => {
         let id = widget.id(&name);
@@ -455,6 +772,31 @@ impl App {
                 }())
               })?;
             }
+            Widget::TapTempo => {
+              let tap = self
+                .document
+                .create_element("button")?
+                .cast::<HtmlButtonElement>()?;
+              tap.set_inner_text("Tap");
+              label.append_child(&tap)?;
+
+              let local = self.this();
+              tap.add_event_listener("click", move || {
+                local.lock().unwrap().beat_detector.tap();
+              })?;
+
+              let auto = self
+                .document
+                .create_element("button")?
+                .cast::<HtmlButtonElement>()?;
+              auto.set_inner_text("Auto");
+              label.append_child(&auto)?;
+
+              let local = self.this();
+              auto.add_event_listener("click", move || {
+                local.lock().unwrap().beat_detector.auto();
+              })?;
+            }
           }
         }
       }
@@ -463,6 +805,97 @@ impl App {
     Ok(())
   }
 
+  fn download(&self, filename: &str, media_type: &str, bytes: &[u8]) -> Result {
+    let a = self
+      .document
+      .create_element("a")?
+      .cast::<HtmlAnchorElement>()?;
+    a.set_download(filename);
+    let mut href = format!("data:{media_type};base64,");
+    base64::encode_config_buf(bytes, base64::STANDARD, &mut href);
+    a.set_href(&href);
+    a.click();
+    Ok(())
+  }
+
+  fn save_png(&self, image: &DynamicImage) -> Result {
+    let metadata = Metadata {
+      script: self.script.clone(),
+      timestamp: js_sys::Date::new_0().to_iso_string().into(),
+    };
+
+    self.download("degenerate.png", "image/png", &metadata.encode(image)?)
+  }
+
+  fn audio(&mut self) -> Result<&mut Audio> {
+    let _promise: Promise = self.audio_context.resume()?;
+
+    Ok(self.audio.get_or_insert_with(|| {
+      Audio::new(
+        &self.audio_context,
+        &self.audio_input_node,
+        &self.analyser_nodes[AudioChannel::Mix as usize],
+      )
+    }))
+  }
+
+  fn on_audio_buffer(&mut self, load: u64, buffer: JsValue) -> Result {
+    if load != self.audio_loads {
+      return Ok(());
+    }
+
+    self.audio()?.load(buffer.cast::<AudioBuffer>()?)
+  }
+
+  // Stop the audio track, and discard tracks that are still being decoded, so
+  // that audio doesn't outlive the script that loaded it
+  fn stop_audio(&mut self) -> Result {
+    self.audio_loads += 1;
+
+    self.gpu.set_offline_audio(None);
+
+    match self.audio.take() {
+      Some(audio) => audio.stop(),
+      None => Ok(()),
+    }
+  }
+
+  // Stop the camera, and discard cameras from requests that are still
+  // pending, so that the camera doesn't outlive the script that started it.
+  // `clear()` is called every frame by most scripts, so it leaves the camera
+  // running.
+  fn stop_camera(&mut self) -> Result {
+    self.camera_requests += 1;
+
+    match self.camera.take() {
+      Some(camera) => self.gpu.stop_camera(camera),
+      None => Ok(()),
+    }
+  }
+
+  fn on_camera_media(
+    &mut self,
+    request: u64,
+    media_stream: JsValue,
+    buffer: String,
+    placement: Placement,
+  ) -> Result {
+    let camera = Camera::new(
+      &self.document,
+      &media_stream.cast::<MediaStream>()?,
+      buffer,
+      placement,
+    )?;
+
+    if request != self.camera_requests {
+      return self.gpu.stop_camera(camera);
+    }
+
+    self.camera = Some(camera);
+
+    Ok(())
+  }
+
   fn on_get_user_media(&mut self, media_stream: JsValue) -> Result {
     let media_stream = media_stream.cast::<MediaStream>()?;
 
@@ -470,7 +903,7 @@ impl App {
       .audio_context
       .create_media_stream_source(&media_stream)?;
 
-    media_stream_audio_source_node.connect_with_audio_node(&self.analyser_node)?;
+    media_stream_audio_source_node.connect_with_audio_node(&self.audio_input_node)?;
 
     self.recording = true;
 
@@ -492,6 +925,101 @@ impl App {
     Ok(())
   }
 
+  fn on_session_input(&mut self) -> Result {
+    let file = self
+      .session_input
+      .files()
+      .and_then(|files| files.get(0))
+      .ok_or("No session file selected")?;
+
+    self.session_input.set_value("");
+
+    let local = self.this();
+    let closure = Closure::wrap(Box::new(move |text: JsValue| {
+      let mut app = local.lock().unwrap();
+      let result = app.on_session_text(text);
+      app.stderr.update(result);
+    }) as Box<dyn FnMut(JsValue)>);
+    let _ = file.text().then(&closure);
+    closure.forget();
+
+    Ok(())
+  }
+
+  fn on_session_text(&mut self, text: JsValue) -> Result {
+    let session = serde_json::from_str::<Session>(
+      &text
+        .as_string()
+        .ok_or("Failed to retrieve session file as a string")?,
+    )?;
+
+    self.html.class_list().remove_1("done")?;
+
+    self.nav.class_list().add_1("fade-out")?;
+
+    // Replace the running script with an empty one, so that it doesn't resume
+    // drawing over the replay when it finishes
+    self.run_script("")?;
+
+    self.gpu.clear()?;
+    self.gpu.present()?;
+
+    self.replay = Some(session.replay(self.frame));
+
+    Ok(())
+  }
+
+  fn on_image_input(&mut self) -> Result {
+    let file = self
+      .image_input
+      .files()
+      .and_then(|files| files.get(0))
+      .ok_or("No image file selected")?;
+
+    self.image_input.set_value("");
+
+    self.load_image_file(file);
+
+    Ok(())
+  }
+
+  fn on_drop(&mut self, event: DragEvent) -> Result {
+    event.prevent_default();
+
+    let file = event
+      .data_transfer()
+      .and_then(|data_transfer| data_transfer.files())
+      .and_then(|files| files.get(0))
+      .ok_or("No file dropped")?;
+
+    self.load_image_file(file);
+
+    Ok(())
+  }
+
+  fn load_image_file(&self, file: File) {
+    let local = self.this();
+    let closure = Closure::wrap(Box::new(move |buffer: JsValue| {
+      let mut app = local.lock().unwrap();
+      let result = app.on_image_buffer(buffer);
+      app.stderr.update(result);
+    }) as Box<dyn FnMut(JsValue)>);
+    let _ = file.array_buffer().then(&closure);
+    closure.forget();
+  }
+
+  fn on_image_buffer(&mut self, buffer: JsValue) -> Result {
+    let script = Metadata::script(&js_sys::Uint8Array::new(&buffer).to_vec())?;
+
+    self.on_input()?;
+
+    self.textarea.set_value(&script);
+
+    self.textarea.focus()?;
+
+    Ok(())
+  }
+
   pub(super) fn on_share(&mut self) -> Result {
     let script = self.textarea.value();
 
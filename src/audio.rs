@@ -0,0 +1,398 @@
+use super::*;
+
+// An audio track, played through the analysers and speakers. In offline mode
+// the track is not played. Instead, its position advances by one frame every
+// animation frame, and it is analyzed in the same way that `AnalyserNode`
+// analyzes audio, so that audio-reactive renders are the same on every run.
+pub(crate) struct Audio {
+  analyser_node: AnalyserNode,
+  audio_context: AudioContext,
+  buffer: Option<AudioBuffer>,
+  channels: [Vec<f32>; 3],
+  frames: u64,
+  input_node: AudioNode,
+  offline: Option<f64>,
+  playing: bool,
+  position: f64,
+  sample_rate: f32,
+  smoothed: [Vec<f32>; 3],
+  source: Option<AudioBufferSourceNode>,
+  started: f64,
+}
+
+impl Audio {
+  // Sources are connected to `input_node`, and offline analysis uses the FFT
+  // size and smoothing of `analyser_node`
+  pub(crate) fn new(
+    audio_context: &AudioContext,
+    input_node: &AudioNode,
+    analyser_node: &AnalyserNode,
+  ) -> Self {
+    Self {
+      analyser_node: analyser_node.clone(),
+      audio_context: audio_context.clone(),
+      buffer: None,
+      channels: Default::default(),
+      frames: 0,
+      input_node: input_node.clone(),
+      offline: None,
+      playing: false,
+      position: 0.0,
+      sample_rate: audio_context.sample_rate(),
+      smoothed: Default::default(),
+      source: None,
+      started: 0.0,
+    }
+  }
+
+  // Replace the track with `buffer`, starting from the beginning
+  pub(crate) fn load(&mut self, buffer: AudioBuffer) -> Result {
+    self.stop_source()?;
+
+    let channels = buffer.number_of_channels();
+
+    // Offline analysis of the mix uses a mono mix of all channels, as
+    // `AnalyserNode` does. Mono tracks are played on both the left and right
+    // channels.
+    let mut mix = vec![0.0; buffer.length() as usize];
+    for channel in 0..channels {
+      for (sample, value) in mix.iter_mut().zip(buffer.get_channel_data(channel)?) {
+        *sample += value / channels as f32;
+      }
+    }
+
+    let left = buffer.get_channel_data(0)?;
+    let right = if channels > 1 {
+      buffer.get_channel_data(1)?
+    } else {
+      left.clone()
+    };
+
+    self.channels = [mix, left, right];
+
+    self.sample_rate = buffer.sample_rate();
+    self.buffer = Some(buffer);
+
+    self.seek(0.0)
+  }
+
+  pub(crate) fn pause(&mut self) -> Result {
+    if self.playing {
+      self.position = self.time();
+      self.frames = 0;
+      self.stop_source()?;
+      self.playing = false;
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn play(&mut self) -> Result {
+    if !self.playing {
+      self.playing = true;
+      self.start_source()?;
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn seek(&mut self, time: f64) -> Result {
+    self.stop_source()?;
+    self.position = time;
+    self.frames = 0;
+    self.smoothed = Default::default();
+
+    if self.playing {
+      self.start_source()?;
+    }
+
+    Ok(())
+  }
+
+  // Enable offline mode, advancing by 1 / `fps` seconds every frame, or
+  // disable it if `fps` is `None`
+  pub(crate) fn set_offline(&mut self, fps: Option<f64>) -> Result {
+    self.position = self.time();
+    self.frames = 0;
+    self.stop_source()?;
+    self.offline = fps;
+
+    if self.playing {
+      self.start_source()?;
+    }
+
+    Ok(())
+  }
+
+  // Stop playing the track, so that it doesn't outlive the script that loaded
+  // it
+  pub(crate) fn stop(mut self) -> Result {
+    self.stop_source()
+  }
+
+  // Advance the offline position by one frame
+  pub(crate) fn tick(&mut self) {
+    if self.offline.is_some() && self.playing && self.buffer.is_some() {
+      self.frames += 1;
+    }
+  }
+
+  // Current position in the track, in seconds, if in offline mode
+  pub(crate) fn offline_time(&self) -> Option<f64> {
+    self.offline.map(|_| self.time())
+  }
+
+  // Current position in the track, in seconds
+  fn time(&self) -> f64 {
+    match self.offline {
+      Some(fps) => self.position + self.frames as f64 / fps,
+      None if self.source.is_some() => self.audio_context.current_time() - self.started,
+      None => self.position,
+    }
+  }
+
+  fn start_source(&mut self) -> Result {
+    let buffer = match &self.buffer {
+      Some(buffer) if self.offline.is_none() => buffer,
+      _ => return Ok(()),
+    };
+
+    let source = self.audio_context.create_buffer_source()?;
+    source.set_buffer(Some(buffer));
+    source.connect_with_audio_node(&self.input_node)?;
+    source.connect_with_audio_node(&self.audio_context.destination())?;
+    source.start_with_when_and_grain_offset(0.0, self.position)?;
+
+    self.started = self.audio_context.current_time() - self.position;
+    self.source = Some(source);
+
+    Ok(())
+  }
+
+  fn stop_source(&mut self) -> Result {
+    if let Some(source) = self.source.take() {
+      source.stop()?;
+      source.disconnect()?;
+    }
+
+    Ok(())
+  }
+
+  // Analyze the track at the current offline position, returning time domain
+  // data and frequency data in decibels for the mix, left, and right
+  // channels, or `None` if not in offline mode.
+  pub(crate) fn analyze(&mut self) -> Option<[(Vec<f32>, Vec<f32>); 3]> {
+    self.offline?;
+
+    let fft_size = self.analyser_node.fft_size() as usize;
+    let smoothing = self.analyser_node.smoothing_time_constant() as f32;
+
+    let end = (self.time() * self.sample_rate as f64) as i64;
+
+    let [mix, left, right] = &mut self.smoothed;
+
+    Some([
+      analyze(&self.channels[0], mix, end, fft_size, smoothing),
+      analyze(&self.channels[1], left, end, fft_size, smoothing),
+      analyze(&self.channels[2], right, end, fft_size, smoothing),
+    ])
+  }
+}
+
+// Analyze the `fft_size` samples before `end`. Frequency data is computed as
+// specified for `AnalyserNode`, with a Blackman window and smoothing over time.
+fn analyze(
+  samples: &[f32],
+  smoothed: &mut Vec<f32>,
+  end: i64,
+  fft_size: usize,
+  smoothing: f32,
+) -> (Vec<f32>, Vec<f32>) {
+  let time_domain = (end - fft_size as i64..end)
+    .map(|i| {
+      usize::try_from(i)
+        .ok()
+        .and_then(|i| samples.get(i))
+        .copied()
+        .unwrap_or_default()
+    })
+    .collect::<Vec<f32>>();
+
+  let mut real = time_domain
+    .iter()
+    .enumerate()
+    .map(|(i, sample)| {
+      let x = i as f32 / fft_size as f32;
+      let window = 0.42 - 0.5 * (TAU * x).cos() + 0.08 * (2.0 * TAU * x).cos();
+      sample * window
+    })
+    .collect::<Vec<f32>>();
+
+  let mut imaginary = vec![0.0; fft_size];
+
+  fft(&mut real, &mut imaginary);
+
+  // Smoothing starts over when the FFT size changes
+  if smoothed.len() != fft_size / 2 {
+    *smoothed = vec![0.0; fft_size / 2];
+  }
+
+  let frequency = smoothed
+    .iter_mut()
+    .zip(real.iter().zip(&imaginary))
+    .map(|(smoothed, (real, imaginary))| {
+      let magnitude = (real * real + imaginary * imaginary).sqrt() / fft_size as f32;
+      *smoothed = smoothing * *smoothed + (1.0 - smoothing) * magnitude;
+      20.0 * smoothed.log10()
+    })
+    .collect::<Vec<f32>>();
+
+  (time_domain, frequency)
+}
+
+// In-place iterative radix-2 fast Fourier transform. `real` and `imaginary`
+// must have the same power of two length.
+fn fft(real: &mut [f32], imaginary: &mut [f32]) {
+  let n = real.len();
+
+  let mut j = 0;
+  for i in 1..n {
+    let mut bit = n >> 1;
+    while j & bit != 0 {
+      j ^= bit;
+      bit >>= 1;
+    }
+    j |= bit;
+
+    if i < j {
+      real.swap(i, j);
+      imaginary.swap(i, j);
+    }
+  }
+
+  let mut len = 2;
+  while len <= n {
+    let angle = -TAU / len as f32;
+    for start in (0..n).step_by(len) {
+      for k in 0..len / 2 {
+        let (sin, cos) = (angle * k as f32).sin_cos();
+        let a = start + k;
+        let b = a + len / 2;
+        let t_real = real[b] * cos - imaginary[b] * sin;
+        let t_imaginary = real[b] * sin + imaginary[b] * cos;
+        real[b] = real[a] - t_real;
+        imaginary[b] = imaginary[a] - t_imaginary;
+        real[a] += t_real;
+        imaginary[a] += t_imaginary;
+      }
+    }
+    len <<= 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn magnitudes(samples: impl Fn(usize) -> f32) -> Vec<f32> {
+    let mut real = (0..64).map(samples).collect::<Vec<f32>>();
+    let mut imaginary = vec![0.0; 64];
+
+    fft(&mut real, &mut imaginary);
+
+    real
+      .iter()
+      .zip(&imaginary)
+      .map(|(real, imaginary)| (real * real + imaginary * imaginary).sqrt())
+      .collect()
+  }
+
+  fn peak(magnitudes: &[f32]) -> usize {
+    (0..magnitudes.len())
+      .max_by(|a, b| magnitudes[*a].total_cmp(&magnitudes[*b]))
+      .unwrap()
+  }
+
+  #[test]
+  fn fft_sine() {
+    let magnitudes = magnitudes(|i| (TAU * 5.0 * i as f32 / 64.0).sin());
+
+    assert_eq!(peak(&magnitudes[..32]), 5);
+    assert!((magnitudes[5] - 32.0).abs() < 1e-3);
+    assert!((magnitudes[59] - 32.0).abs() < 1e-3);
+
+    for (i, magnitude) in magnitudes.iter().enumerate() {
+      if i != 5 && i != 59 {
+        assert!(*magnitude < 1e-3, "bin {i}: {magnitude}");
+      }
+    }
+  }
+
+  #[test]
+  fn fft_dc() {
+    let magnitudes = magnitudes(|_| 1.0);
+
+    assert!((magnitudes[0] - 64.0).abs() < 1e-3);
+
+    for magnitude in &magnitudes[1..] {
+      assert!(*magnitude < 1e-3);
+    }
+  }
+
+  #[test]
+  fn fft_nyquist() {
+    let magnitudes = magnitudes(|i| if i % 2 == 0 { 1.0 } else { -1.0 });
+
+    assert_eq!(peak(&magnitudes), 32);
+    assert!((magnitudes[32] - 64.0).abs() < 1e-3);
+
+    for (i, magnitude) in magnitudes.iter().enumerate() {
+      if i != 32 {
+        assert!(*magnitude < 1e-3, "bin {i}: {magnitude}");
+      }
+    }
+  }
+
+  #[test]
+  fn analyze_sine() {
+    // A 1500 Hz tone falls in bin 64 of a 2048 point FFT at 48 kHz
+    let samples = (0..48000)
+      .map(|i| (TAU * 1500.0 * i as f32 / 48000.0).sin())
+      .collect::<Vec<f32>>();
+
+    let (time_domain, frequency) = analyze(&samples, &mut Vec::new(), 24000, 2048, 0.0);
+
+    assert_eq!(time_domain.len(), 2048);
+    assert_eq!(frequency.len(), 1024);
+    assert_eq!(peak(&frequency), 64);
+  }
+
+  #[test]
+  fn analyze_frames() {
+    let samples = (0..48000).map(|i| i as f32).collect::<Vec<f32>>();
+
+    let mut smoothed = Vec::new();
+
+    // At 60 frames per second and 48 kHz, each frame advances 800 samples,
+    // and analyzes the samples before the end of the frame
+    for frame in 0..60 {
+      let end = frame * 800;
+
+      let (time_domain, frequency) = analyze(&samples, &mut smoothed, end, 256, 0.8);
+
+      assert_eq!(time_domain.len(), 256);
+      assert_eq!(frequency.len(), 128);
+      assert_eq!(smoothed.len(), 128);
+
+      for (i, sample) in (end - 256..end).zip(&time_domain) {
+        assert_eq!(*sample, i.max(0) as f32);
+      }
+    }
+
+    // Smoothing starts over when the FFT size changes
+    let (_, frequency) = analyze(&samples, &mut smoothed, 48000, 512, 0.8);
+
+    assert_eq!(frequency.len(), 256);
+    assert_eq!(smoothed.len(), 256);
+  }
+}
@@ -0,0 +1,313 @@
+use super::*;
+
+// Detects onsets as peaks in spectral flux, estimates tempo from the intervals
+// between recent onsets, and runs a beat clock at that tempo which is pulled
+// into phase by onsets that land near a predicted beat. Tapping overrides the
+// estimated tempo and phase until `auto` is called.
+pub(crate) struct BeatDetector {
+  bpm: f32,
+  flux: VecDeque<(f64, f32)>,
+  last_onset: f64,
+  next_beat: Option<f64>,
+  onsets: VecDeque<f64>,
+  previous: Vec<f32>,
+  tap_pending: bool,
+  taps: Vec<f64>,
+}
+
+impl BeatDetector {
+  const FLUX_WINDOW: f64 = 1000.0;
+  const MAX_BPM: f32 = 160.0;
+  const MIN_BPM: f32 = 80.0;
+  const ONSET_WINDOW: f64 = 8000.0;
+  const REFRACTORY: f64 = 100.0;
+  const SENSITIVITY: f32 = 1.5;
+  const TAP_TIMEOUT: f64 = 2000.0;
+
+  pub(crate) fn new() -> Self {
+    Self {
+      bpm: 0.0,
+      flux: VecDeque::new(),
+      last_onset: f64::NEG_INFINITY,
+      next_beat: None,
+      onsets: VecDeque::new(),
+      previous: Vec::new(),
+      tap_pending: false,
+      taps: Vec::new(),
+    }
+  }
+
+  // Return to detecting tempo from the audio
+  pub(crate) fn auto(&mut self) {
+    self.tap_pending = false;
+    self.taps.clear();
+  }
+
+  // Register a tap, which takes effect on the next update, so that taps and
+  // audio share a clock
+  pub(crate) fn tap(&mut self) {
+    self.tap_pending = true;
+  }
+
+  // Update with `frequency`, normalized frequency data, at `time`, in
+  // milliseconds
+  pub(crate) fn update(&mut self, frequency: &[f32], time: f64) -> Tempo {
+    let onset = self.detect_onset(frequency, time);
+
+    if self.tap_pending {
+      self.tap_pending = false;
+      self.register_tap(time);
+    }
+
+    let tapped = self.taps.len() >= 2;
+
+    if onset && !tapped {
+      self.onsets.push_back(time);
+      self.estimate_bpm();
+    }
+
+    while self
+      .onsets
+      .front()
+      .is_some_and(|&onset| time - onset > Self::ONSET_WINDOW)
+    {
+      self.onsets.pop_front();
+    }
+
+    if self.bpm == 0.0 {
+      return Tempo {
+        beat: onset,
+        bpm: 0.0,
+        phase: 0.0,
+      };
+    }
+
+    let period = self.period();
+
+    // Restart the clock if time jumped backwards, for example when switching
+    // to offline mode
+    let mut next_beat = self
+      .next_beat
+      .filter(|&next_beat| next_beat - time <= period)
+      .unwrap_or(time);
+
+    let mut beat = false;
+
+    if time >= next_beat {
+      beat = true;
+      while next_beat <= time {
+        next_beat += period;
+      }
+    }
+
+    if onset && !tapped {
+      let until = next_beat - time;
+      if until < period * 0.2 {
+        beat = true;
+        next_beat = time + period;
+      } else if until > period * 0.8 {
+        next_beat = time + period;
+      }
+    }
+
+    self.next_beat = Some(next_beat);
+
+    Tempo {
+      beat,
+      bpm: self.bpm,
+      phase: (1.0 - (next_beat - time) / period) as f32,
+    }
+  }
+
+  fn detect_onset(&mut self, frequency: &[f32], time: f64) -> bool {
+    if self.previous.len() != frequency.len() {
+      self.previous = frequency.to_vec();
+      return false;
+    }
+
+    let flux = frequency
+      .iter()
+      .zip(&self.previous)
+      .map(|(current, previous)| (current - previous).max(0.0))
+      .sum::<f32>()
+      / frequency.len().max(1) as f32;
+
+    self.previous.copy_from_slice(frequency);
+
+    while self
+      .flux
+      .front()
+      .is_some_and(|&(then, _)| time - then > Self::FLUX_WINDOW)
+    {
+      self.flux.pop_front();
+    }
+
+    let count = self.flux.len().max(1) as f32;
+    let mean = self.flux.iter().map(|(_, flux)| flux).sum::<f32>() / count;
+    let variance = self
+      .flux
+      .iter()
+      .map(|(_, flux)| (flux - mean).powi(2))
+      .sum::<f32>()
+      / count;
+
+    let warm = self
+      .flux
+      .front()
+      .is_some_and(|&(then, _)| time - then >= Self::FLUX_WINDOW / 2.0);
+
+    self.flux.push_back((time, flux));
+
+    let onset = warm
+      && flux > 0.001
+      && flux > Self::SENSITIVITY * (mean + variance.sqrt())
+      && time - self.last_onset > Self::REFRACTORY;
+
+    if onset {
+      self.last_onset = time;
+    }
+
+    onset
+  }
+
+  // Estimate tempo from the intervals between every pair of recent onsets,
+  // folded into the range of supported tempos, by finding the most common
+  // tempo and averaging the intervals that agree with it
+  fn estimate_bpm(&mut self) {
+    let mut candidates = Vec::new();
+
+    for (i, a) in self.onsets.iter().enumerate() {
+      for b in self.onsets.iter().skip(i + 1) {
+        let mut bpm = (60000.0 / (b - a)) as f32;
+
+        while bpm < Self::MIN_BPM {
+          bpm *= 2.0;
+        }
+
+        while bpm >= Self::MAX_BPM {
+          bpm /= 2.0;
+        }
+
+        candidates.push(bpm);
+      }
+    }
+
+    let best = candidates
+      .iter()
+      .map(|&candidate| {
+        let votes = candidates
+          .iter()
+          .filter(|&&other| (other - candidate).abs() < 2.0)
+          .count();
+        (votes, candidate)
+      })
+      .max_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+
+    if let Some((votes, best)) = best {
+      if votes >= 2 {
+        self.bpm = candidates
+          .iter()
+          .filter(|&&other| (other - best).abs() < 2.0)
+          .sum::<f32>()
+          / votes as f32;
+      }
+    }
+  }
+
+  fn period(&self) -> f64 {
+    60000.0 / self.bpm as f64
+  }
+
+  fn register_tap(&mut self, time: f64) {
+    if self
+      .taps
+      .last()
+      .is_some_and(|&last| time - last > Self::TAP_TIMEOUT)
+    {
+      self.taps.clear();
+    }
+
+    self.taps.push(time);
+
+    if self.taps.len() > 8 {
+      self.taps.remove(0);
+    }
+
+    if let (Some(first), Some(last)) = (self.taps.first(), self.taps.last()) {
+      if self.taps.len() >= 2 {
+        self.bpm = (60000.0 * (self.taps.len() - 1) as f64 / (last - first)) as f32;
+        self.next_beat = Some(*last);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FRAME: f64 = 1000.0 / 60.0;
+
+  #[test]
+  fn onsets() {
+    let mut detector = BeatDetector::new();
+
+    let mut tempo = None;
+    let mut beats = 0;
+
+    // Ten seconds at 60 frames per second, with an impulse every 30 frames,
+    // or 120 times a minute
+    for frame in 0..600 {
+      let level = if frame % 30 == 0 { 1.0 } else { 0.0 };
+      let update = detector.update(&[level; 16], frame as f64 * FRAME);
+      if update.beat {
+        beats += 1;
+      }
+      tempo = Some(update);
+    }
+
+    let tempo = tempo.unwrap();
+
+    assert!((tempo.bpm - 120.0).abs() < 1.0, "{}", tempo.bpm);
+    assert!((18..=20).contains(&beats), "{beats}");
+  }
+
+  fn tap(detector: &mut BeatDetector, time: f64) -> Tempo {
+    detector.tap();
+    detector.update(&[0.0; 16], time)
+  }
+
+  #[test]
+  fn taps() {
+    let mut detector = BeatDetector::new();
+
+    for time in [0.0, 400.0, 800.0] {
+      tap(&mut detector, time);
+    }
+
+    let tempo = tap(&mut detector, 1200.0);
+
+    assert_eq!(tempo.bpm, 150.0);
+    assert!(tempo.beat);
+    assert_eq!(tempo.phase, 0.0);
+
+    let tempo = detector.update(&[0.0; 16], 1400.0);
+
+    assert_eq!(tempo.bpm, 150.0);
+    assert!(!tempo.beat);
+    assert_eq!(tempo.phase, 0.5);
+  }
+
+  #[test]
+  fn stale_taps() {
+    let mut detector = BeatDetector::new();
+
+    tap(&mut detector, 0.0);
+    assert_eq!(tap(&mut detector, 500.0).bpm, 120.0);
+
+    // Taps after a pause longer than the timeout start a new sequence, rather
+    // than averaging with the old one
+    tap(&mut detector, 3500.0);
+    assert_eq!(tap(&mut detector, 3750.0).bpm, 240.0);
+  }
+}
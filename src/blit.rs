@@ -0,0 +1,85 @@
+use super::*;
+
+// Blit `source`, a texture of size `source_size` holding an image stored from
+// top to bottom, into `destination`, which holds the region of an image of
+// size `size` whose bottom left corner is at `offset`. The source is scaled to
+// fit within or fill the image, centered, and flipped right side up. Areas of
+// the destination not covered by the source are cleared to black.
+pub(crate) fn blit(
+  gl: &WebGl2RenderingContext,
+  source: &WebGlTexture,
+  (source_width, source_height): (u32, u32),
+  placement: Placement,
+  destination: &WebGlTexture,
+  (width, height): (u32, u32),
+  (x, y): (i32, i32),
+) -> Result {
+  let scale_x = width as f32 / source_width as f32;
+  let scale_y = height as f32 / source_height as f32;
+
+  let scale = match placement {
+    Placement::Fit => scale_x.min(scale_y),
+    Placement::Fill => scale_x.max(scale_y),
+  };
+
+  let placed_width = (source_width as f32 * scale).round() as i32;
+  let placed_height = (source_height as f32 * scale).round() as i32;
+
+  let left = (width as i32 - placed_width) / 2 - x;
+  let bottom = (height as i32 - placed_height) / 2 - y;
+
+  let read_frame_buffer = gl
+    .create_framebuffer()
+    .ok_or("Failed to create framebuffer")?;
+
+  let draw_frame_buffer = gl
+    .create_framebuffer()
+    .ok_or("Failed to create framebuffer")?;
+
+  gl.bind_framebuffer(
+    WebGl2RenderingContext::READ_FRAMEBUFFER,
+    Some(&read_frame_buffer),
+  );
+
+  gl.framebuffer_texture_2d(
+    WebGl2RenderingContext::READ_FRAMEBUFFER,
+    WebGl2RenderingContext::COLOR_ATTACHMENT0,
+    WebGl2RenderingContext::TEXTURE_2D,
+    Some(source),
+    0,
+  );
+
+  gl.bind_framebuffer(
+    WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+    Some(&draw_frame_buffer),
+  );
+
+  gl.framebuffer_texture_2d(
+    WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+    WebGl2RenderingContext::COLOR_ATTACHMENT0,
+    WebGl2RenderingContext::TEXTURE_2D,
+    Some(destination),
+    0,
+  );
+
+  gl.clear_bufferfv_with_f32_array(WebGl2RenderingContext::COLOR, 0, &[0.0, 0.0, 0.0, 1.0]);
+
+  gl.blit_framebuffer(
+    0,
+    0,
+    source_width as i32,
+    source_height as i32,
+    left,
+    bottom + placed_height,
+    left + placed_width,
+    bottom,
+    WebGl2RenderingContext::COLOR_BUFFER_BIT,
+    WebGl2RenderingContext::LINEAR,
+  );
+
+  gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+  gl.delete_framebuffer(Some(&read_frame_buffer));
+  gl.delete_framebuffer(Some(&draw_frame_buffer));
+
+  Ok(())
+}
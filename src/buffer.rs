@@ -0,0 +1,68 @@
+use super::*;
+
+// A named render buffer. Renders sample from `source` and write to
+// `destination`, after which the two textures are swapped.
+pub(crate) struct Buffer {
+  pub(crate) destination: WebGlTexture,
+  pub(crate) source: WebGlTexture,
+}
+
+impl Buffer {
+  pub(crate) fn new(
+    gl: &WebGl2RenderingContext,
+    width: u32,
+    height: u32,
+    precision: Precision,
+  ) -> Result<Self> {
+    Ok(Self {
+      destination: Self::create_texture(gl, width, height, precision)?,
+      source: Self::create_texture(gl, width, height, precision)?,
+    })
+  }
+
+  pub(crate) fn delete(&self, gl: &WebGl2RenderingContext) {
+    gl.delete_texture(Some(&self.source));
+    gl.delete_texture(Some(&self.destination));
+  }
+
+  pub(crate) fn swap(&mut self) {
+    mem::swap(&mut self.source, &mut self.destination);
+  }
+
+  pub(crate) fn create_texture(
+    gl: &WebGl2RenderingContext,
+    width: u32,
+    height: u32,
+    precision: Precision,
+  ) -> Result<WebGlTexture> {
+    let texture = gl.create_texture().ok_or("Failed to create texture")?;
+
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+    gl.tex_storage_2d(
+      WebGl2RenderingContext::TEXTURE_2D,
+      1,
+      match precision {
+        Precision::Rgba8 => WebGl2RenderingContext::RGBA8,
+        Precision::Rgba16f => WebGl2RenderingContext::RGBA16F,
+        Precision::Rgba32f => WebGl2RenderingContext::RGBA32F,
+      },
+      width as i32,
+      height as i32,
+    );
+
+    gl.tex_parameteri(
+      WebGl2RenderingContext::TEXTURE_2D,
+      WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+      WebGl2RenderingContext::NEAREST.try_into()?,
+    );
+
+    gl.tex_parameteri(
+      WebGl2RenderingContext::TEXTURE_2D,
+      WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+      WebGl2RenderingContext::NEAREST.try_into()?,
+    );
+
+    Ok(texture)
+  }
+}
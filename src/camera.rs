@@ -0,0 +1,119 @@
+use super::*;
+
+// A camera video stream, drawn into a buffer every animation frame. Video
+// frames are uploaded into `source`, which is reallocated only when the video
+// size or precision changes.
+pub(crate) struct Camera {
+  pub(crate) buffer: String,
+  media_stream: MediaStream,
+  pub(crate) placement: Placement,
+  source: Option<(u32, u32, Precision, WebGlTexture)>,
+  pub(crate) video: HtmlVideoElement,
+}
+
+impl Camera {
+  pub(crate) fn new(
+    document: &Document,
+    media_stream: &MediaStream,
+    buffer: String,
+    placement: Placement,
+  ) -> Result<Self> {
+    let video = document
+      .create_element("video")?
+      .cast::<HtmlVideoElement>()?;
+
+    video.set_muted(true);
+    video.set_attribute("playsinline", "")?;
+    video.set_src_object(Some(media_stream));
+
+    let _promise: Promise = video.play()?;
+
+    Ok(Self {
+      buffer,
+      media_stream: media_stream.clone(),
+      placement,
+      source: None,
+      video,
+    })
+  }
+
+  // Stop the camera's tracks, turning the camera off, and delete the source
+  // texture
+  pub(crate) fn stop(self, gl: &WebGl2RenderingContext) -> Result {
+    self.video.set_src_object(None);
+
+    for track in self.media_stream.get_tracks().iter() {
+      track.cast::<MediaStreamTrack>()?.stop();
+    }
+
+    if let Some((_, _, _, texture)) = self.source {
+      gl.delete_texture(Some(&texture));
+    }
+
+    Ok(())
+  }
+
+  // Draw the current video frame into `texture`, which holds the region of an
+  // image of size `size` whose bottom left corner is at `offset`. Nothing is
+  // drawn until the first frame is available.
+  pub(crate) fn draw(
+    &mut self,
+    gl: &WebGl2RenderingContext,
+    texture: &WebGlTexture,
+    precision: Precision,
+    size: (u32, u32),
+    offset: (i32, i32),
+  ) -> Result {
+    let width = self.video.video_width();
+    let height = self.video.video_height();
+
+    if self.video.ready_state() < HtmlMediaElement::HAVE_CURRENT_DATA || width == 0 || height == 0 {
+      return Ok(());
+    }
+
+    match &self.source {
+      Some((source_width, source_height, source_precision, _))
+        if (*source_width, *source_height, *source_precision) == (width, height, precision) => {}
+      _ => {
+        if let Some((_, _, _, texture)) = self.source.take() {
+          gl.delete_texture(Some(&texture));
+        }
+
+        self.source = Some((
+          width,
+          height,
+          precision,
+          Buffer::create_texture(gl, width, height, precision)?,
+        ));
+      }
+    }
+
+    let source = &self.source.as_ref().unwrap().3;
+
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(source));
+
+    gl.tex_sub_image_2d_with_u32_and_u32_and_html_video_element(
+      WebGl2RenderingContext::TEXTURE_2D,
+      0,
+      0,
+      0,
+      WebGl2RenderingContext::RGBA,
+      if precision == Precision::Rgba8 {
+        WebGl2RenderingContext::UNSIGNED_BYTE
+      } else {
+        WebGl2RenderingContext::FLOAT
+      },
+      &self.video,
+    )?;
+
+    blit(
+      gl,
+      source,
+      (width, height),
+      self.placement,
+      texture,
+      size,
+      offset,
+    )
+  }
+}
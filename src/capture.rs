@@ -0,0 +1,87 @@
+use super::*;
+
+pub(crate) struct Capture {
+  format: CaptureFormat,
+  fps: u32,
+  frames: Vec<RgbaImage>,
+  remaining: u32,
+}
+
+impl Capture {
+  pub(crate) fn new(frames: u32, fps: u32, format: CaptureFormat) -> Self {
+    Self {
+      format,
+      fps,
+      frames: Vec::new(),
+      remaining: frames,
+    }
+  }
+
+  pub(crate) fn is_done(&self) -> bool {
+    self.remaining == 0
+  }
+
+  pub(crate) fn push(&mut self, frame: RgbaImage) {
+    self.frames.push(frame);
+    self.remaining = self.remaining.saturating_sub(1);
+  }
+
+  pub(crate) fn filename(&self) -> &'static str {
+    match self.format {
+      CaptureFormat::Apng => "degenerate.png",
+      CaptureFormat::Gif => "degenerate.gif",
+      CaptureFormat::Zip => "degenerate.zip",
+    }
+  }
+
+  pub(crate) fn media_type(&self) -> &'static str {
+    match self.format {
+      CaptureFormat::Apng => "image/apng",
+      CaptureFormat::Gif => "image/gif",
+      CaptureFormat::Zip => "application/zip",
+    }
+  }
+
+  pub(crate) fn encode(self) -> Result<Vec<u8>> {
+    let first = self.frames.first().ok_or("No frames were captured")?;
+
+    let (width, height) = first.dimensions();
+
+    let mut output = Vec::new();
+
+    match self.format {
+      CaptureFormat::Apng => {
+        let mut encoder = png::Encoder::new(&mut output, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len().try_into()?, 0)?;
+        encoder.set_frame_delay(1, self.fps.try_into()?)?;
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+          writer.write_image_data(frame.as_raw())?;
+        }
+        writer.finish()?;
+      }
+      CaptureFormat::Gif => {
+        let mut encoder = GifEncoder::new(&mut output);
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(self.frames.into_iter().map(|frame| {
+          image::Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(1000, self.fps))
+        }))?;
+      }
+      CaptureFormat::Zip => {
+        let mut zip = ZipWriter::new(Cursor::new(&mut output));
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (i, frame) in self.frames.iter().enumerate() {
+          let mut png = Cursor::new(Vec::new());
+          frame.write_to(&mut png, ImageOutputFormat::Png)?;
+          zip.start_file(format!("degenerate-{i:05}.png"), options)?;
+          zip.write_all(png.get_ref())?;
+        }
+        zip.finish()?;
+      }
+    }
+
+    Ok(output)
+  }
+}
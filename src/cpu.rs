@@ -0,0 +1,665 @@
+use super::*;
+
+const FFT_SIZE: usize = 2048;
+
+#[derive(Debug)]
+pub enum CpuError {
+  CustomField,
+}
+
+impl Display for CpuError {
+  fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+    match self {
+      Self::CustomField => write!(f, "Custom fields are not supported by the CPU renderer"),
+    }
+  }
+}
+
+impl std::error::Error for CpuError {}
+
+pub struct Cpu {
+  audio_frequency: [Vec<f32>; 3],
+  audio_spectrogram: [Vec<Vec<f32>>; 3],
+  audio_time_domain: [Vec<f32>; 3],
+  buffers: BTreeMap<String, RgbaImage>,
+  height: u32,
+  presented: String,
+  width: u32,
+}
+
+impl Cpu {
+  pub fn new(resolution: u32) -> Self {
+    Self::with_dimensions(resolution, resolution)
+  }
+
+  pub fn with_dimensions(width: u32, height: u32) -> Self {
+    Self::with_image(RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255])))
+  }
+
+  pub fn with_image(image: RgbaImage) -> Self {
+    Self {
+      audio_frequency: std::array::from_fn(|_| vec![0.0; FFT_SIZE / 2]),
+      audio_spectrogram: std::array::from_fn(|_| vec![vec![0.0; FFT_SIZE / 2]]),
+      audio_time_domain: std::array::from_fn(|_| vec![0.0; FFT_SIZE]),
+      height: image.height(),
+      presented: "default".into(),
+      width: image.width(),
+      buffers: BTreeMap::from([("default".into(), image)]),
+    }
+  }
+
+  pub fn buffer(&self, name: &str) -> Option<&RgbaImage> {
+    self.buffers.get(name)
+  }
+
+  pub fn clear(&mut self) {
+    self.buffers.clear();
+    self.create_buffer(&self.presented.clone());
+  }
+
+  pub fn image(&self) -> &RgbaImage {
+    &self.buffers[&self.presented]
+  }
+
+  pub fn into_image(mut self) -> RgbaImage {
+    self.buffers.remove(&self.presented).unwrap()
+  }
+
+  pub fn present(&mut self, name: &str) {
+    self.create_buffer(name);
+    self.presented = name.into();
+  }
+
+  pub fn dimensions(&self) -> (u32, u32) {
+    (self.width, self.height)
+  }
+
+  // The extent of the buffer in position coordinates, where the longer axis
+  // spans [-1, 1]
+  fn extent(&self) -> Vector2 {
+    let resolution = self.width.max(self.height) as f32;
+    Vector2::new(self.width as f32, self.height as f32) / resolution
+  }
+
+  fn resolution(&self) -> Vector2 {
+    Vector2::new(self.width as f32, self.height as f32)
+  }
+
+  pub fn set_audio_frequency(&mut self, channel: AudioChannel, audio_frequency: Vec<f32>) {
+    self.audio_frequency[channel as usize] = audio_frequency;
+  }
+
+  // Rows of frequency data, from oldest to newest
+  pub fn set_audio_spectrogram(&mut self, channel: AudioChannel, audio_spectrogram: Vec<Vec<f32>>) {
+    self.audio_spectrogram[channel as usize] = audio_spectrogram;
+  }
+
+  pub fn set_audio_time_domain(&mut self, channel: AudioChannel, audio_time_domain: Vec<f32>) {
+    self.audio_time_domain[channel as usize] = audio_time_domain;
+  }
+
+  pub fn render(&mut self, filter: &Filter) -> Result<(), CpuError> {
+    Self::check_field(&filter.field)?;
+
+    self.create_buffer(&filter.source);
+    self.create_buffer(&filter.destination);
+
+    if let Some(mask) = &filter.mask {
+      self.create_buffer(mask);
+    }
+
+    for _ in 0..filter.times {
+      let source = &self.buffers[&filter.source];
+      let original = &self.buffers[&filter.destination];
+      let mask = filter.mask.as_ref().map(|mask| &self.buffers[mask]);
+      let destination = RgbaImage::from_fn(self.width, self.height, |x, y| {
+        self.fragment(
+          filter,
+          source,
+          original,
+          mask,
+          Vector2::new(x as f32 + 0.5, (self.height - y) as f32 - 0.5),
+        )
+      });
+      self.buffers.insert(filter.destination.clone(), destination);
+    }
+
+    Ok(())
+  }
+
+  // Custom fields are GLSL source, which can only be evaluated on the GPU
+  fn check_field(field: &Field) -> Result<(), CpuError> {
+    match field {
+      Field::Custom(_) => Err(CpuError::CustomField),
+      Field::Intersect(a, b)
+      | Field::SmoothUnion { a, b, .. }
+      | Field::Subtract(a, b)
+      | Field::Union(a, b) => {
+        Self::check_field(a)?;
+        Self::check_field(b)
+      }
+      Field::Transform { field, .. } => Self::check_field(field),
+      _ => Ok(()),
+    }
+  }
+
+  fn create_buffer(&mut self, name: &str) {
+    if !self.buffers.contains_key(name) {
+      self.buffers.insert(
+        name.into(),
+        RgbaImage::from_pixel(self.width, self.height, Rgba([0, 0, 0, 255])),
+      );
+    }
+  }
+
+  fn fragment(
+    &self,
+    filter: &Filter,
+    source: &RgbaImage,
+    original: &RgbaImage,
+    mask: Option<&RgbaImage>,
+    frag_coord: Vector2,
+  ) -> Rgba<u8> {
+    let resolution = self.resolution();
+    let extent = self.extent();
+
+    let uv = frag_coord.component_div(&resolution);
+
+    let position = (uv * 2.0 - Vector2::repeat(1.0)).component_mul(&extent);
+
+    let transformed = (filter.position_transform * position.push(1.0)).xy();
+
+    let wrapped = transformed.zip_map(&extent, |n, e| match filter.wrap {
+      Wrap::Repeat => glsl::modulo(n + e, 2.0 * e) - e,
+      Wrap::Mirror => e - (glsl::modulo(n + e, 4.0 * e) - 2.0 * e).abs(),
+      Wrap::Clamp => n.clamp(-e, e),
+      Wrap::DefaultColor => n,
+    });
+
+    let input_color = if filter.coordinates {
+      quadrant(wrapped.component_div(&extent)).push(0.0)
+    } else if wrapped.x.abs() <= extent.x && wrapped.y.abs() <= extent.y {
+      self.sample_source(filter, source, quadrant(wrapped.component_div(&extent)))
+    } else {
+      Vector3::from(filter.default_color)
+    };
+
+    let original_color = self.sample(original, uv);
+
+    let mask_value = match mask {
+      Some(mask) => self.sample(mask, uv).dot(&Vector3::repeat(1.0 / 3.0)),
+      None => 1.0,
+    };
+
+    let color_vector = to_color_space(filter.color_space, input_color);
+
+    let transformed_color_vector = filter.color_transform * color_vector.push(1.0);
+
+    let transformed_color = from_color_space(filter.color_space, transformed_color_vector.xyz());
+
+    let distance = self.distance_field(&filter.field, filter.audio_channel, wrapped);
+
+    let mut coverage = if filter.feather > 0.0 {
+      (0.5 - distance / filter.feather).clamp(0.0, 1.0)
+    } else if distance <= 0.0 {
+      1.0
+    } else {
+      0.0
+    };
+
+    if filter.glow > 0.0 && distance > 0.0 {
+      coverage = coverage.max((-distance / filter.glow).exp());
+    }
+
+    let alpha = filter.alpha * mask_value * coverage;
+
+    let blended_color = blend_color(filter.blend, transformed_color, original_color);
+
+    let output_color = blended_color * alpha + original_color * (1.0 - alpha);
+
+    Rgba([
+      glsl::unorm(output_color.x),
+      glsl::unorm(output_color.y),
+      glsl::unorm(output_color.z),
+      255,
+    ])
+  }
+
+  fn sample_source(&self, filter: &Filter, source: &RgbaImage, uv: Vector2) -> Vector3 {
+    let p = uv.component_mul(&self.resolution()) - Vector2::repeat(0.5);
+    let i = p.map(|n| n.floor() as i32);
+    let f = p - p.map(f32::floor);
+
+    let texel = |x: i32, y: i32| self.source_texel(filter.wrap, source, i.x + x, i.y + y);
+
+    match filter.sampling {
+      Sampling::Nearest => self.sample(source, uv),
+      Sampling::Bilinear => glsl::mix(
+        glsl::mix(texel(0, 0), texel(1, 0), f.x),
+        glsl::mix(texel(0, 1), texel(1, 1), f.x),
+        f.y,
+      ),
+      Sampling::Bicubic => {
+        let wx = cubic_weights(f.x);
+        let wy = cubic_weights(f.y);
+        let mut color = Vector3::zeros();
+        for (y, wy) in wy.iter().enumerate() {
+          for (x, wx) in wx.iter().enumerate() {
+            color += texel(x as i32 - 1, y as i32 - 1) * *wx * *wy;
+          }
+        }
+        color.map(|n| n.clamp(0.0, 1.0))
+      }
+    }
+  }
+
+  fn source_texel(&self, wrap: Wrap, source: &RgbaImage, x: i32, y: i32) -> Vector3 {
+    let wrap_index = |i: i32, size: i32| match wrap {
+      Wrap::Repeat => i.rem_euclid(size),
+      Wrap::Mirror => {
+        let j = i.rem_euclid(size * 2);
+        if j < size {
+          j
+        } else {
+          size * 2 - 1 - j
+        }
+      }
+      Wrap::Clamp | Wrap::DefaultColor => i.clamp(0, size - 1),
+    };
+
+    let (width, height) = (self.width as i32, self.height as i32);
+
+    let Rgba([r, g, b, _]) = *source.get_pixel(
+      wrap_index(x, width) as u32,
+      (height - 1 - wrap_index(y, height)) as u32,
+    );
+
+    Vector3::new(r as f32, g as f32, b as f32) / 255.0
+  }
+
+  fn sample(&self, image: &RgbaImage, uv: Vector2) -> Vector3 {
+    let x = glsl::texel(uv.x, self.width);
+    let y = glsl::texel(uv.y, self.height);
+    let Rgba([r, g, b, _]) = *image.get_pixel(x, self.height - 1 - y);
+    Vector3::new(r as f32, g as f32, b as f32) / 255.0
+  }
+
+  fn audio_frequency_sample(&self, channel: AudioChannel, p: Vector2) -> f32 {
+    let audio_frequency = &self.audio_frequency[channel as usize];
+    audio_frequency[glsl::texel(quadrant(p).x, audio_frequency.len() as u32) as usize]
+  }
+
+  fn audio_spectrogram_sample(&self, channel: AudioChannel, p: Vector2) -> f32 {
+    let uv = quadrant(p);
+    let audio_spectrogram = &self.audio_spectrogram[channel as usize];
+    let row = &audio_spectrogram[glsl::texel(uv.y, audio_spectrogram.len() as u32) as usize];
+    row[glsl::texel(uv.x, row.len() as u32) as usize]
+  }
+
+  fn audio_time_domain_sample(&self, channel: AudioChannel, p: Vector2) -> f32 {
+    let audio_time_domain = &self.audio_time_domain[channel as usize];
+    audio_time_domain[glsl::texel(quadrant(p).x, audio_time_domain.len() as u32) as usize]
+  }
+
+  fn distance_field(&self, field: &Field, channel: AudioChannel, p: Vector2) -> f32 {
+    let px = quadrant(p.component_div(&self.extent()))
+      .component_mul(&self.resolution())
+      .map(|n| n as u32);
+
+    match *field {
+      Field::All => -1.0,
+      Field::Check => {
+        let i = (p + Vector2::repeat(1.0)) * 4.0;
+        if (i.x as i32) % 2 != (i.y as i32) % 2 {
+          -1.0
+        } else {
+          1.0
+        }
+      }
+      Field::Circle { radius } => p.norm() - radius,
+      Field::Cross { size, thickness } => field_cross(p, size, thickness, 0.0),
+      Field::Custom(_) => unreachable!("custom fields are rejected by `render`"),
+      Field::Equalizer => quadrant(p).y - self.audio_frequency_sample(channel, p),
+      Field::Frequency { threshold } => threshold - self.audio_frequency_sample(channel, p),
+      Field::Intersect(ref a, ref b) => self
+        .distance_field(a, channel, p)
+        .max(self.distance_field(b, channel, p)),
+      Field::Mod { divisor, remainder } => {
+        if divisor == 0 {
+          1.0
+        } else if px.y.wrapping_mul(self.width).wrapping_add(px.x) % divisor == remainder {
+          -1.0
+        } else {
+          1.0
+        }
+      }
+      Field::Rows { on, off } => {
+        if px
+          .y
+          .checked_rem(on.wrapping_add(off))
+          .is_some_and(|row| row < on)
+        {
+          -1.0
+        } else {
+          1.0
+        }
+      }
+      Field::SmoothUnion { ref a, ref b, k } => field_smooth_union(
+        self.distance_field(a, channel, p),
+        self.distance_field(b, channel, p),
+        k,
+      ),
+      Field::Spectrogram { threshold } => threshold - self.audio_spectrogram_sample(channel, p),
+      Field::Square { width, height } => field_box(p, width / 2.0, height / 2.0),
+      Field::Subtract(ref a, ref b) => self
+        .distance_field(a, channel, p)
+        .max(-self.distance_field(b, channel, p)),
+      Field::TimeDomain => -self.audio_time_domain_sample(channel, p).abs(),
+      Field::Top => -p.y,
+      Field::Transform {
+        ref field,
+        transform,
+      } => self.distance_field(field, channel, (transform * p.push(1.0)).xy()),
+      Field::Union(ref a, ref b) => self
+        .distance_field(a, channel, p)
+        .min(self.distance_field(b, channel, p)),
+      Field::Wave { thickness } => {
+        (p.y - self.audio_time_domain_sample(channel, p)).abs() - thickness
+      }
+      Field::X { size, radius } => field_x(p, size, radius),
+    }
+  }
+}
+
+fn blend_color(blend: Blend, s: Vector3, d: Vector3) -> Vector3 {
+  match blend {
+    Blend::Normal => s,
+    Blend::Add => (s + d).map(|n| n.min(1.0)),
+    Blend::Multiply => s.component_mul(&d),
+    Blend::Screen => s + d - s.component_mul(&d),
+    Blend::Overlay => s.zip_map(&d, |s, d| {
+      if d < 0.5 {
+        2.0 * s * d
+      } else {
+        1.0 - 2.0 * (1.0 - s) * (1.0 - d)
+      }
+    }),
+    Blend::Difference => (s - d).abs(),
+    Blend::Lighten => s.sup(&d),
+    Blend::Darken => s.inf(&d),
+    Blend::Xor => s.zip_map(&d, |s, d| (glsl::unorm(s) ^ glsl::unorm(d)) as f32 / 255.0),
+  }
+}
+
+fn rgb_to_hsv(c: Vector3) -> Vector3 {
+  let maximum = c.x.max(c.y.max(c.z));
+  let minimum = c.x.min(c.y.min(c.z));
+  let delta = maximum - minimum;
+
+  let hue = if delta > 0.0 {
+    if maximum == c.x {
+      glsl::modulo((c.y - c.z) / delta, 6.0)
+    } else if maximum == c.y {
+      (c.z - c.x) / delta + 2.0
+    } else {
+      (c.x - c.y) / delta + 4.0
+    }
+  } else {
+    0.0
+  };
+
+  let saturation = if maximum > 0.0 { delta / maximum } else { 0.0 };
+
+  Vector3::new(hue / 6.0, saturation, maximum)
+}
+
+fn hsv_to_rgb(c: Vector3) -> Vector3 {
+  Vector3::new(5.0, 3.0, 1.0).map(|n| {
+    let k = glsl::modulo(n + glsl::fract(c.x) * 6.0, 6.0);
+    c.z - c.z * c.y * k.min(4.0 - k).clamp(0.0, 1.0)
+  })
+}
+
+fn rgb_to_hsl(c: Vector3) -> Vector3 {
+  let maximum = c.x.max(c.y.max(c.z));
+  let minimum = c.x.min(c.y.min(c.z));
+  let lightness = (maximum + minimum) / 2.0;
+  let saturation = if lightness > 0.0 && lightness < 1.0 {
+    (maximum - lightness) / lightness.min(1.0 - lightness)
+  } else {
+    0.0
+  };
+  Vector3::new(rgb_to_hsv(c).x, saturation, lightness)
+}
+
+fn hsl_to_rgb(c: Vector3) -> Vector3 {
+  let a = c.y * c.z.min(1.0 - c.z);
+  Vector3::new(0.0, 8.0, 4.0).map(|n| {
+    let k = glsl::modulo(n + glsl::fract(c.x) * 12.0, 12.0);
+    c.z - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+  })
+}
+
+fn srgb_to_linear(c: Vector3) -> Vector3 {
+  c.map(|n| {
+    if n > 0.04045 {
+      ((n + 0.055) / 1.055).powf(2.4)
+    } else {
+      n / 12.92
+    }
+  })
+}
+
+fn linear_to_srgb(c: Vector3) -> Vector3 {
+  c.map(|n| {
+    let n = n.max(0.0);
+    if n > 0.0031308 {
+      1.055 * n.powf(1.0 / 2.4) - 0.055
+    } else {
+      n * 12.92
+    }
+  })
+}
+
+#[allow(clippy::excessive_precision)]
+fn rgb_to_oklab(c: Vector3) -> Vector3 {
+  let c = srgb_to_linear(c);
+
+  let lms = Vector3::new(
+    0.4122214708 * c.x + 0.5363325363 * c.y + 0.0514459929 * c.z,
+    0.2119034982 * c.x + 0.6806995451 * c.y + 0.1073969566 * c.z,
+    0.0883024619 * c.x + 0.2817188376 * c.y + 0.6299787005 * c.z,
+  )
+  .map(|n| n.max(0.0).powf(1.0 / 3.0));
+
+  Vector3::new(
+    0.2104542553 * lms.x + 0.7936177850 * lms.y - 0.0040720468 * lms.z,
+    1.9779984951 * lms.x - 2.4285922050 * lms.y + 0.4505937099 * lms.z,
+    0.0259040371 * lms.x + 0.7827717662 * lms.y - 0.8086757660 * lms.z,
+  )
+}
+
+#[allow(clippy::excessive_precision)]
+fn oklab_to_rgb(c: Vector3) -> Vector3 {
+  let lms = Vector3::new(
+    c.x + 0.3963377774 * c.y + 0.2158037573 * c.z,
+    c.x - 0.1055613458 * c.y - 0.0638541728 * c.z,
+    c.x - 0.0894841775 * c.y - 1.2914855480 * c.z,
+  )
+  .map(|n| n * n * n);
+
+  linear_to_srgb(Vector3::new(
+    4.0767416621 * lms.x - 3.3077115913 * lms.y + 0.2309699292 * lms.z,
+    -1.2684380046 * lms.x + 2.6097574011 * lms.y - 0.3413193965 * lms.z,
+    -0.0041960863 * lms.x - 0.7034186147 * lms.y + 1.7076147010 * lms.z,
+  ))
+}
+
+fn rgb_to_oklch(c: Vector3) -> Vector3 {
+  let lab = rgb_to_oklab(c);
+  Vector3::new(
+    lab.x,
+    lab.yz().norm(),
+    glsl::fract(lab.z.atan2(lab.y) / TAU),
+  )
+}
+
+fn oklch_to_rgb(c: Vector3) -> Vector3 {
+  oklab_to_rgb(Vector3::new(
+    c.x,
+    c.y * (c.z * TAU).cos(),
+    c.y * (c.z * TAU).sin(),
+  ))
+}
+
+fn to_color_space(color_space: ColorSpace, c: Vector3) -> Vector3 {
+  match color_space {
+    ColorSpace::Rgb => c * 2.0 - Vector3::repeat(1.0),
+    ColorSpace::Hsv => rgb_to_hsv(c),
+    ColorSpace::Hsl => rgb_to_hsl(c),
+    ColorSpace::Oklab => rgb_to_oklab(c),
+    ColorSpace::Oklch => rgb_to_oklch(c),
+  }
+}
+
+fn from_color_space(color_space: ColorSpace, c: Vector3) -> Vector3 {
+  match color_space {
+    ColorSpace::Rgb => octant(c),
+    ColorSpace::Hsv => hsv_to_rgb(c),
+    ColorSpace::Hsl => hsl_to_rgb(c),
+    ColorSpace::Oklab => oklab_to_rgb(c),
+    ColorSpace::Oklch => oklch_to_rgb(c),
+  }
+}
+
+fn cubic_weights(f: f32) -> [f32; 4] {
+  [
+    f * (-0.5 + f * (1.0 - 0.5 * f)),
+    1.0 + f * f * (-2.5 + 1.5 * f),
+    f * (0.5 + f * (2.0 - 1.5 * f)),
+    f * f * (-0.5 + 0.5 * f),
+  ]
+}
+
+fn quadrant(position: Vector2) -> Vector2 {
+  (position + Vector2::repeat(1.0)) / 2.0
+}
+
+fn octant(position: Vector3) -> Vector3 {
+  (position + Vector3::repeat(1.0)) / 2.0
+}
+
+fn field_box(p: Vector2, width: f32, height: f32) -> f32 {
+  let d = p.abs() - Vector2::new(width, height);
+  d.sup(&Vector2::zeros()).norm() + d.x.max(d.y).min(0.0)
+}
+
+fn field_cross(p: Vector2, size: f32, thickness: f32, radius: f32) -> f32 {
+  let b = Vector2::new(size, thickness);
+  let p = p.abs();
+  let p = if p.y > p.x { p.yx() } else { p };
+  let q = p - b;
+  let k = q.y.max(q.x);
+  let w = if k > 0.0 {
+    q
+  } else {
+    Vector2::new(thickness - p.x, -k)
+  };
+  glsl::sign(k) * w.sup(&Vector2::zeros()).norm() + radius
+}
+
+fn field_smooth_union(a: f32, b: f32, k: f32) -> f32 {
+  if k <= 0.0 {
+    return a.min(b);
+  }
+  let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+  b + (a - b) * h - k * h * (1.0 - h)
+}
+
+fn field_x(p: Vector2, size: f32, radius: f32) -> f32 {
+  let p = p.abs();
+  (p - Vector2::repeat((p.x + p.y).min(size) * 0.5)).norm() - radius
+}
+
+mod glsl {
+  use super::*;
+
+  pub(super) fn fract(x: f32) -> f32 {
+    x - x.floor()
+  }
+
+  pub(super) fn mix(x: Vector3, y: Vector3, a: f32) -> Vector3 {
+    x * (1.0 - a) + y * a
+  }
+
+  pub(super) fn modulo(x: f32, y: f32) -> f32 {
+    x - y * (x / y).floor()
+  }
+
+  pub(super) fn sign(x: f32) -> f32 {
+    if x > 0.0 {
+      1.0
+    } else if x < 0.0 {
+      -1.0
+    } else {
+      0.0
+    }
+  }
+
+  pub(super) fn texel(coordinate: f32, size: u32) -> u32 {
+    ((modulo(coordinate, 1.0) * size as f32) as u32).min(size - 1)
+  }
+
+  pub(super) fn unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn custom_fields_are_rejected() {
+    let custom = Field::Custom("float f(vec2 p) { return 0.0; }".into());
+
+    let mut cpu = Cpu::new(4);
+
+    assert!(matches!(
+      cpu.render(&Filter::new().field(custom.clone())),
+      Err(CpuError::CustomField)
+    ));
+
+    assert!(matches!(
+      cpu.render(&Filter::new().field(Field::Top.union(custom))),
+      Err(CpuError::CustomField)
+    ));
+
+    assert!(cpu.render(&Filter::new().x()).is_ok());
+  }
+
+  #[test]
+  fn spectrogram_row_order() {
+    let mut cpu = Cpu::new(4);
+
+    // Only the lowest bin of the newest row is loud
+    let mut spectrogram = vec![vec![0.0; 4]; 4];
+    spectrogram[3][0] = 1.0;
+    cpu.set_audio_spectrogram(AudioChannel::Left, spectrogram);
+
+    let spectrogram = Filter::new().field(Field::Spectrogram { threshold: 0.5 });
+
+    cpu
+      .render(&spectrogram.clone().audio_channel(AudioChannel::Left))
+      .unwrap();
+
+    for (x, y, pixel) in cpu.image().enumerate_pixels() {
+      assert_eq!(pixel[0] != 0, (x, y) == (0, 0), "pixel ({x}, {y})");
+    }
+
+    cpu.clear();
+    cpu.render(&spectrogram).unwrap();
+
+    assert!(cpu.image().pixels().all(|pixel| pixel[0] == 0));
+  }
+}
@@ -33,12 +33,42 @@ impl From<&str> for Error {
   }
 }
 
+impl From<base64::DecodeError> for Error {
+  fn from(e: base64::DecodeError) -> Self {
+    Self::Rust(e.into())
+  }
+}
+
 impl From<ImageError> for Error {
   fn from(e: ImageError) -> Self {
     Self::Rust(e.into())
   }
 }
 
+impl From<io::Error> for Error {
+  fn from(e: io::Error) -> Self {
+    Self::Rust(e.into())
+  }
+}
+
+impl From<png::DecodingError> for Error {
+  fn from(e: png::DecodingError) -> Self {
+    Self::Rust(e.into())
+  }
+}
+
+impl From<png::EncodingError> for Error {
+  fn from(e: png::EncodingError) -> Self {
+    Self::Rust(e.into())
+  }
+}
+
+impl From<ZipError> for Error {
+  fn from(e: ZipError) -> Self {
+    Self::Rust(e.into())
+  }
+}
+
 impl From<serde_json::Error> for Error {
   fn from(e: serde_json::Error) -> Self {
     Self::Rust(e.into())
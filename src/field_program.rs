@@ -0,0 +1,197 @@
+use super::*;
+
+const MAX_INSTRUCTIONS: usize = 32;
+const MAX_STACK: usize = 8;
+const MAX_TRANSFORMS: usize = 8;
+
+// Opcodes, which must match the `FIELD_*` constants in `fragment.glsl`
+const FIELD_ALL: i32 = 0;
+const FIELD_CHECK: i32 = 1;
+const FIELD_CIRCLE: i32 = 2;
+const FIELD_CROSS: i32 = 3;
+const FIELD_EQUALIZER: i32 = 4;
+const FIELD_FREQUENCY: i32 = 5;
+const FIELD_MOD: i32 = 6;
+const FIELD_ROWS: i32 = 7;
+const FIELD_SQUARE: i32 = 8;
+const FIELD_TIME_DOMAIN: i32 = 9;
+const FIELD_TOP: i32 = 10;
+const FIELD_WAVE: i32 = 11;
+const FIELD_X: i32 = 12;
+const FIELD_UNION: i32 = 13;
+const FIELD_INTERSECT: i32 = 14;
+const FIELD_SUBTRACT: i32 = 15;
+const FIELD_SMOOTH_UNION: i32 = 16;
+const FIELD_PUSH_TRANSFORM: i32 = 17;
+const FIELD_POP_TRANSFORM: i32 = 18;
+const FIELD_CUSTOM: i32 = 19;
+const FIELD_SPECTROGRAM: i32 = 20;
+
+// A field compiled to the instruction encoding that `distance_field` in
+// `fragment.glsl` evaluates.
+#[derive(Default)]
+pub(crate) struct FieldProgram {
+  // Whether the field may read audio data. Custom fields may sample the audio
+  // textures, so are assumed to.
+  pub(crate) audio: bool,
+  pub(crate) integers: Vec<u32>,
+  pub(crate) opcodes: Vec<i32>,
+  pub(crate) parameters: Vec<f32>,
+  pub(crate) sources: Vec<String>,
+  stack: usize,
+  pub(crate) transforms: Vec<f32>,
+}
+
+impl FieldProgram {
+  pub(crate) fn new(field: &Field) -> Result<Self> {
+    let mut program = Self::default();
+    program.compile(field)?;
+    Ok(program)
+  }
+
+  fn compile(&mut self, field: &Field) -> Result {
+    if matches!(
+      field,
+      Field::Custom(_)
+        | Field::Equalizer
+        | Field::Frequency { .. }
+        | Field::Spectrogram { .. }
+        | Field::TimeDomain
+        | Field::Wave { .. }
+    ) {
+      self.audio = true;
+    }
+
+    match field {
+      Field::All => self.primitive(FIELD_ALL, [0.0; 2], [0; 2]),
+      Field::Check => self.primitive(FIELD_CHECK, [0.0; 2], [0; 2]),
+      Field::Circle { radius } => self.primitive(FIELD_CIRCLE, [*radius, 0.0], [0; 2]),
+      Field::Custom(source) => {
+        let index = match self.sources.iter().position(|s| s == source) {
+          Some(index) => index,
+          None => {
+            self.sources.push(source.clone());
+            self.sources.len() - 1
+          }
+        };
+
+        self.primitive(FIELD_CUSTOM, [0.0; 2], [index.try_into()?, 0])
+      }
+      Field::Cross { size, thickness } => self.primitive(FIELD_CROSS, [*size, *thickness], [0; 2]),
+      Field::Equalizer => self.primitive(FIELD_EQUALIZER, [0.0; 2], [0; 2]),
+      Field::Frequency { threshold } => self.primitive(FIELD_FREQUENCY, [*threshold, 0.0], [0; 2]),
+      Field::Intersect(a, b) => self.combinator(FIELD_INTERSECT, a, b, 0.0),
+      Field::Mod { divisor, remainder } => {
+        self.primitive(FIELD_MOD, [0.0; 2], [*divisor, *remainder])
+      }
+      Field::Rows { on, off } => self.primitive(FIELD_ROWS, [0.0; 2], [*on, *off]),
+      Field::SmoothUnion { a, b, k } => self.combinator(FIELD_SMOOTH_UNION, a, b, *k),
+      Field::Spectrogram { threshold } => {
+        self.primitive(FIELD_SPECTROGRAM, [*threshold, 0.0], [0; 2])
+      }
+      Field::Square { width, height } => self.primitive(FIELD_SQUARE, [*width, *height], [0; 2]),
+      Field::Subtract(a, b) => self.combinator(FIELD_SUBTRACT, a, b, 0.0),
+      Field::TimeDomain => self.primitive(FIELD_TIME_DOMAIN, [0.0; 2], [0; 2]),
+      Field::Top => self.primitive(FIELD_TOP, [0.0; 2], [0; 2]),
+      Field::Transform { field, transform } => {
+        let index = self.transforms.len() / 9;
+
+        if index == MAX_TRANSFORMS {
+          return Err(format!("Fields may contain at most {MAX_TRANSFORMS} transforms").into());
+        }
+
+        self.transforms.extend_from_slice(transform.as_slice());
+        self.instruction(FIELD_PUSH_TRANSFORM, [0.0; 2], [index.try_into()?, 0])?;
+
+        self.compile(field)?;
+
+        self.instruction(FIELD_POP_TRANSFORM, [0.0; 2], [0; 2])
+      }
+      Field::Union(a, b) => self.combinator(FIELD_UNION, a, b, 0.0),
+      Field::Wave { thickness } => self.primitive(FIELD_WAVE, [*thickness, 0.0], [0; 2]),
+      Field::X { size, radius } => self.primitive(FIELD_X, [*size, *radius], [0; 2]),
+    }
+  }
+
+  fn combinator(&mut self, opcode: i32, a: &Field, b: &Field, k: f32) -> Result {
+    self.compile(a)?;
+    self.compile(b)?;
+    self.stack -= 1;
+    self.instruction(opcode, [k, 0.0], [0; 2])
+  }
+
+  fn primitive(&mut self, opcode: i32, parameters: [f32; 2], integers: [u32; 2]) -> Result {
+    if self.stack == MAX_STACK {
+      return Err(format!("Fields may be nested at most {MAX_STACK} deep").into());
+    }
+
+    self.stack += 1;
+    self.instruction(opcode, parameters, integers)
+  }
+
+  fn instruction(&mut self, opcode: i32, parameters: [f32; 2], integers: [u32; 2]) -> Result {
+    if self.opcodes.len() == MAX_INSTRUCTIONS {
+      return Err(format!("Fields may contain at most {MAX_INSTRUCTIONS} instructions").into());
+    }
+
+    self.opcodes.push(opcode);
+    self
+      .parameters
+      .extend_from_slice(&[parameters[0], parameters[1], 0.0, 0.0]);
+    self.integers.extend_from_slice(&integers);
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn constants_match_fragment_shader() {
+    let constants = include_str!("fragment.glsl")
+      .lines()
+      .filter_map(|line| line.strip_prefix("const int ")?.strip_suffix(';'))
+      .filter_map(|line| line.split_once(" = "))
+      .map(|(name, value)| (name, value.parse::<i32>().unwrap()))
+      .collect::<BTreeMap<&str, i32>>();
+
+    for (name, value) in [
+      ("FIELD_ALL", FIELD_ALL),
+      ("FIELD_CHECK", FIELD_CHECK),
+      ("FIELD_CIRCLE", FIELD_CIRCLE),
+      ("FIELD_CROSS", FIELD_CROSS),
+      ("FIELD_EQUALIZER", FIELD_EQUALIZER),
+      ("FIELD_FREQUENCY", FIELD_FREQUENCY),
+      ("FIELD_MOD", FIELD_MOD),
+      ("FIELD_ROWS", FIELD_ROWS),
+      ("FIELD_SQUARE", FIELD_SQUARE),
+      ("FIELD_TIME_DOMAIN", FIELD_TIME_DOMAIN),
+      ("FIELD_TOP", FIELD_TOP),
+      ("FIELD_WAVE", FIELD_WAVE),
+      ("FIELD_X", FIELD_X),
+      ("FIELD_UNION", FIELD_UNION),
+      ("FIELD_INTERSECT", FIELD_INTERSECT),
+      ("FIELD_SUBTRACT", FIELD_SUBTRACT),
+      ("FIELD_SMOOTH_UNION", FIELD_SMOOTH_UNION),
+      ("FIELD_PUSH_TRANSFORM", FIELD_PUSH_TRANSFORM),
+      ("FIELD_POP_TRANSFORM", FIELD_POP_TRANSFORM),
+      ("FIELD_CUSTOM", FIELD_CUSTOM),
+      ("FIELD_SPECTROGRAM", FIELD_SPECTROGRAM),
+      ("MAX_FIELD_INSTRUCTIONS", MAX_INSTRUCTIONS as i32),
+      ("MAX_FIELD_STACK", MAX_STACK as i32),
+      ("MAX_FIELD_TRANSFORMS", MAX_TRANSFORMS as i32),
+    ] {
+      assert_eq!(constants.get(name), Some(&value), "{name}");
+    }
+
+    assert_eq!(
+      constants
+        .keys()
+        .filter(|name| name.starts_with("FIELD_"))
+        .count(),
+      21,
+    );
+  }
+}
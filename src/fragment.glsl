@@ -15,22 +15,73 @@ const int FIELD_TIME_DOMAIN = 9;
 const int FIELD_TOP = 10;
 const int FIELD_WAVE = 11;
 const int FIELD_X = 12;
+const int FIELD_UNION = 13;
+const int FIELD_INTERSECT = 14;
+const int FIELD_SUBTRACT = 15;
+const int FIELD_SMOOTH_UNION = 16;
+const int FIELD_PUSH_TRANSFORM = 17;
+const int FIELD_POP_TRANSFORM = 18;
+const int FIELD_CUSTOM = 19;
+const int FIELD_SPECTROGRAM = 20;
+
+const int BLEND_NORMAL = 0;
+const int BLEND_ADD = 1;
+const int BLEND_MULTIPLY = 2;
+const int BLEND_SCREEN = 3;
+const int BLEND_OVERLAY = 4;
+const int BLEND_DIFFERENCE = 5;
+const int BLEND_LIGHTEN = 6;
+const int BLEND_DARKEN = 7;
+const int BLEND_XOR = 8;
+
+const int COLOR_SPACE_RGB = 0;
+const int COLOR_SPACE_HSV = 1;
+const int COLOR_SPACE_HSL = 2;
+const int COLOR_SPACE_OKLAB = 3;
+const int COLOR_SPACE_OKLCH = 4;
+
+const int SAMPLING_NEAREST = 0;
+const int SAMPLING_BILINEAR = 1;
+const int SAMPLING_BICUBIC = 2;
+
+const int WRAP_DEFAULT_COLOR = 0;
+const int WRAP_REPEAT = 1;
+const int WRAP_MIRROR = 2;
+const int WRAP_CLAMP = 3;
+
+const float TAU = 6.283185307179586;
+
+const int MAX_FIELD_INSTRUCTIONS = 32;
+const int MAX_FIELD_STACK = 8;
+const int MAX_FIELD_TRANSFORMS = 8;
 
 uniform bool coordinates;
-uniform bool wrap;
+uniform bool masked;
 uniform float alpha;
-uniform float resolution;
-uniform int field;
+uniform float feather;
+uniform float glow;
+uniform int audio_spectrogram_row;
+uniform int blend;
+uniform int color_space;
+uniform int sampling;
+uniform int wrap;
+uniform int field_instruction_count;
+uniform int field_opcodes[MAX_FIELD_INSTRUCTIONS];
+uniform mat3 field_transforms[MAX_FIELD_TRANSFORMS];
 uniform mat3 position_transform;
 uniform mat4 color_transform;
 uniform sampler2D audio_frequency;
+uniform sampler2D audio_spectrogram;
 uniform sampler2D audio_time_domain;
+uniform sampler2D mask;
+uniform sampler2D original;
 uniform sampler2D source;
-uniform uint mod_divisor;
-uniform uint mod_remainder;
-uniform uint rows_off;
-uniform uint rows_on;
+uniform uvec2 field_integers[MAX_FIELD_INSTRUCTIONS];
+uniform vec2 extent;
+uniform vec2 offset;
+uniform vec2 resolution;
 uniform vec3 default_color;
+uniform vec4 field_parameters[MAX_FIELD_INSTRUCTIONS];
 
 out vec4 output_color;
 
@@ -42,10 +93,240 @@ vec3 octant(vec3 position) {
   return (position + 1.0) / 2.0;
 }
 
+// Wrap position `p` within [-extent, extent] according to the wrap mode
+vec2 wrap_position(vec2 p) {
+  switch (wrap) {
+    case WRAP_REPEAT:
+      return mod(p + extent, 2.0 * extent) - extent;
+    case WRAP_MIRROR:
+      return extent - abs(mod(p + extent, 4.0 * extent) - 2.0 * extent);
+    case WRAP_CLAMP:
+      return clamp(p, -extent, extent);
+    default:
+      return p;
+  }
+}
+
+// Wrap texel index `i` within the source texture according to the wrap mode
+ivec2 wrap_texel(ivec2 i) {
+  ivec2 size = ivec2(resolution);
+  switch (wrap) {
+    case WRAP_REPEAT:
+      return ivec2(mod(vec2(i), vec2(size)));
+    case WRAP_MIRROR: {
+      ivec2 j = ivec2(mod(vec2(i), vec2(size * 2)));
+      return ivec2(
+        j.x < size.x ? j.x : size.x * 2 - 1 - j.x,
+        j.y < size.y ? j.y : size.y * 2 - 1 - j.y
+      );
+    }
+    default:
+      return clamp(i, ivec2(0), size - 1);
+  }
+}
+
+// Convert `uv` within the image to `uv` within source. When rendering a tile,
+// source only contains the tile, so coordinates outside of it are clamped to
+// its edge.
+vec2 tile_uv(vec2 uv) {
+  return clamp((uv * resolution - offset) / vec2(textureSize(source, 0)), 0.0, 1.0);
+}
+
+// Fetch texel `i` of the image from source. When rendering a tile, source
+// only contains the tile, so texels outside of it are clamped to its edge.
+vec3 source_texel(ivec2 i) {
+  ivec2 texel = wrap_texel(i) - ivec2(offset);
+  return texelFetch(source, clamp(texel, ivec2(0), textureSize(source, 0) - 1), 0).rgb;
+}
+
+vec4 cubic_weights(float f) {
+  return vec4(
+    f * (-0.5 + f * (1.0 - 0.5 * f)),
+    1.0 + f * f * (-2.5 + 1.5 * f),
+    f * (0.5 + f * (2.0 - 1.5 * f)),
+    f * f * (-0.5 + 0.5 * f)
+  );
+}
+
+// Sample source at `uv` using the sampling mode. Bilinear and bicubic
+// sampling interpolate between texel centers, and bicubic sampling uses
+// Catmull-Rom weights.
+vec3 sample_source(vec2 uv) {
+  vec2 p = uv * resolution - 0.5;
+  ivec2 i = ivec2(floor(p));
+  vec2 f = p - floor(p);
+
+  switch (sampling) {
+    case SAMPLING_BILINEAR:
+      return mix(
+        mix(source_texel(i), source_texel(i + ivec2(1, 0)), f.x),
+        mix(source_texel(i + ivec2(0, 1)), source_texel(i + ivec2(1, 1)), f.x),
+        f.y
+      );
+    case SAMPLING_BICUBIC: {
+      vec4 wx = cubic_weights(f.x);
+      vec4 wy = cubic_weights(f.y);
+      vec3 color = vec3(0.0);
+      for (int y = 0; y < 4; y++) {
+        for (int x = 0; x < 4; x++) {
+          color += source_texel(i + ivec2(x - 1, y - 1)) * wx[x] * wy[y];
+        }
+      }
+      return clamp(color, 0.0, 1.0);
+    }
+    default:
+      return texture(source, tile_uv(uv)).rgb;
+  }
+}
+
+vec3 rgb_to_hsv(vec3 c) {
+  float maximum = max(c.r, max(c.g, c.b));
+  float minimum = min(c.r, min(c.g, c.b));
+  float delta = maximum - minimum;
+
+  float hue = 0.0;
+  if (delta > 0.0) {
+    if (maximum == c.r) {
+      hue = mod((c.g - c.b) / delta, 6.0);
+    } else if (maximum == c.g) {
+      hue = (c.b - c.r) / delta + 2.0;
+    } else {
+      hue = (c.r - c.g) / delta + 4.0;
+    }
+  }
+
+  float saturation = maximum > 0.0 ? delta / maximum : 0.0;
+
+  return vec3(hue / 6.0, saturation, maximum);
+}
+
+vec3 hsv_to_rgb(vec3 c) {
+  vec3 k = mod(vec3(5.0, 3.0, 1.0) + fract(c.x) * 6.0, 6.0);
+  return c.z - c.z * c.y * clamp(min(k, 4.0 - k), 0.0, 1.0);
+}
+
+vec3 rgb_to_hsl(vec3 c) {
+  float maximum = max(c.r, max(c.g, c.b));
+  float minimum = min(c.r, min(c.g, c.b));
+  float lightness = (maximum + minimum) / 2.0;
+  float saturation = lightness > 0.0 && lightness < 1.0
+    ? (maximum - lightness) / min(lightness, 1.0 - lightness)
+    : 0.0;
+  return vec3(rgb_to_hsv(c).x, saturation, lightness);
+}
+
+vec3 hsl_to_rgb(vec3 c) {
+  vec3 k = mod(vec3(0.0, 8.0, 4.0) + fract(c.x) * 12.0, 12.0);
+  float a = c.y * min(c.z, 1.0 - c.z);
+  return c.z - a * clamp(min(k - 3.0, 9.0 - k), -1.0, 1.0);
+}
+
+vec3 srgb_to_linear(vec3 c) {
+  return mix(
+    c / 12.92,
+    pow((c + 0.055) / 1.055, vec3(2.4)),
+    greaterThan(c, vec3(0.04045))
+  );
+}
+
+vec3 linear_to_srgb(vec3 c) {
+  c = max(c, 0.0);
+  return mix(
+    c * 12.92,
+    1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055,
+    greaterThan(c, vec3(0.0031308))
+  );
+}
+
+// See https://bottosson.github.io/posts/oklab/
+vec3 rgb_to_oklab(vec3 c) {
+  c = srgb_to_linear(c);
+
+  vec3 lms = vec3(
+    0.4122214708 * c.r + 0.5363325363 * c.g + 0.0514459929 * c.b,
+    0.2119034982 * c.r + 0.6806995451 * c.g + 0.1073969566 * c.b,
+    0.0883024619 * c.r + 0.2817188376 * c.g + 0.6299787005 * c.b
+  );
+
+  lms = pow(max(lms, 0.0), vec3(1.0 / 3.0));
+
+  return vec3(
+    0.2104542553 * lms.x + 0.7936177850 * lms.y - 0.0040720468 * lms.z,
+    1.9779984951 * lms.x - 2.4285922050 * lms.y + 0.4505937099 * lms.z,
+    0.0259040371 * lms.x + 0.7827717662 * lms.y - 0.8086757660 * lms.z
+  );
+}
+
+vec3 oklab_to_rgb(vec3 c) {
+  vec3 lms = vec3(
+    c.x + 0.3963377774 * c.y + 0.2158037573 * c.z,
+    c.x - 0.1055613458 * c.y - 0.0638541728 * c.z,
+    c.x - 0.0894841775 * c.y - 1.2914855480 * c.z
+  );
+
+  lms = lms * lms * lms;
+
+  return linear_to_srgb(vec3(
+    4.0767416621 * lms.x - 3.3077115913 * lms.y + 0.2309699292 * lms.z,
+    -1.2684380046 * lms.x + 2.6097574011 * lms.y - 0.3413193965 * lms.z,
+    -0.0041960863 * lms.x - 0.7034186147 * lms.y + 1.7076147010 * lms.z
+  ));
+}
+
+// Oklch hue is expressed in turns, like HSV and HSL hue
+vec3 rgb_to_oklch(vec3 c) {
+  vec3 lab = rgb_to_oklab(c);
+  return vec3(lab.x, length(lab.yz), fract(atan(lab.z, lab.y) / TAU));
+}
+
+vec3 oklch_to_rgb(vec3 c) {
+  return oklab_to_rgb(vec3(c.x, c.y * cos(c.z * TAU), c.y * sin(c.z * TAU)));
+}
+
+// Convert color to a vector in the current color space. RGB colors are
+// converted from [0,1] to [-1,1], so that rotations are about middle gray.
+vec3 to_color_space(vec3 c) {
+  switch (color_space) {
+    case COLOR_SPACE_HSV:
+      return rgb_to_hsv(c);
+    case COLOR_SPACE_HSL:
+      return rgb_to_hsl(c);
+    case COLOR_SPACE_OKLAB:
+      return rgb_to_oklab(c);
+    case COLOR_SPACE_OKLCH:
+      return rgb_to_oklch(c);
+    default:
+      return c * 2.0 - 1.0;
+  }
+}
+
+vec3 from_color_space(vec3 c) {
+  switch (color_space) {
+    case COLOR_SPACE_HSV:
+      return hsv_to_rgb(c);
+    case COLOR_SPACE_HSL:
+      return hsl_to_rgb(c);
+    case COLOR_SPACE_OKLAB:
+      return oklab_to_rgb(c);
+    case COLOR_SPACE_OKLCH:
+      return oklch_to_rgb(c);
+    default:
+      return octant(c);
+  }
+}
+
 float audio_frequency_sample(vec2 position) {
   return texture(audio_frequency, quadrant(position)).r;
 }
 
+// The spectrogram is a ring buffer of rows of frequency data, with the oldest
+// row at `audio_spectrogram_row`, so that y runs from oldest to newest
+float audio_spectrogram_sample(vec2 position) {
+  vec2 uv = quadrant(position);
+  uv.y += float(audio_spectrogram_row) / float(textureSize(audio_spectrogram, 0).y);
+  return texture(audio_spectrogram, uv).r;
+}
+
 float audio_time_domain_sample(vec2 position) {
   return texture(audio_time_domain, quadrant(position)).r;
 }
@@ -88,7 +369,7 @@ float field_frequency(vec2 p, float threshold) {
 float field_mod(uvec2 px, uint divisor, uint remainder) {
   if (divisor == 0u) {
     return 1.0;
-  } else if ((px.y * uint(resolution) + px.x) % divisor == remainder) {
+  } else if ((px.y * uint(resolution.x) + px.x) % divisor == remainder) {
     return -1.0;
   } else {
     return 1.0;
@@ -107,6 +388,10 @@ float field_rows(uvec2 p, uint nrows, uint step) {
   }
 }
 
+float field_spectrogram(vec2 p, float threshold) {
+  return threshold - audio_spectrogram_sample(p);
+}
+
 float field_box(vec2 p, float width, float height) {
   vec2 d = abs(p) - vec2(width, height);
   return length(max(d, 0.0)) + min(max(d.x, d.y), 0.0);
@@ -129,80 +414,199 @@ float field_x(vec2 p, float size, float radius) {
   return length(p - min(p.x + p.y, size) * 0.5) - radius;
 }
 
-float distance_field(vec2 p, uvec2 px) {
-  switch (field) {
+float field_smooth_union(float a, float b, float k) {
+  if (k <= 0.0) {
+    return min(a, b);
+  }
+  float h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+  return mix(b, a, h) - k * h * (1.0 - h);
+}
+
+// Blend transformed color `s` with original color `d`
+vec3 blend_color(vec3 s, vec3 d) {
+  switch (blend) {
+    case BLEND_ADD:
+      return min(s + d, 1.0);
+    case BLEND_MULTIPLY:
+      return s * d;
+    case BLEND_SCREEN:
+      return s + d - s * d;
+    case BLEND_OVERLAY:
+      return mix(
+        2.0 * s * d,
+        1.0 - 2.0 * (1.0 - s) * (1.0 - d),
+        step(0.5, d)
+      );
+    case BLEND_DIFFERENCE:
+      return abs(s - d);
+    case BLEND_LIGHTEN:
+      return max(s, d);
+    case BLEND_DARKEN:
+      return min(s, d);
+    case BLEND_XOR:
+      return vec3(
+        uvec3(round(clamp(s, 0.0, 1.0) * 255.0)) ^ uvec3(round(clamp(d, 0.0, 1.0) * 255.0))
+      ) / 255.0;
+    default:
+      return s;
+  }
+}
+
+// Defined after `main`, by `Program`, which appends custom field sources
+float field_custom(uint index, vec2 p);
+
+float field_primitive(int opcode, vec4 parameters, uvec2 integers, vec2 p) {
+  // Calculate position in pixel coordinates, [0, resolution)
+  uvec2 px = uvec2(quadrant(p / extent) * resolution);
+
+  switch (opcode) {
     case FIELD_ALL:
       return field_all();
     case FIELD_CHECK:
       return field_check(p);
     case FIELD_CIRCLE:
-      return field_circle(p, 1.0);
+      return field_circle(p, parameters.x);
     case FIELD_CROSS:
-      return field_cross(p, 1.0, 0.25, 0.0);
+      return field_cross(p, parameters.x, parameters.y, 0.0);
     case FIELD_EQUALIZER:
       return field_equalizer(p);
     case FIELD_FREQUENCY:
-      return field_frequency(p, 0.125);
+      return field_frequency(p, parameters.x);
     case FIELD_MOD:
-      return field_mod(px, mod_divisor, mod_remainder);
+      return field_mod(px, integers.x, integers.y);
     case FIELD_ROWS:
-      return field_rows(px, rows_on, rows_off);
+      return field_rows(px, integers.x, integers.y);
     case FIELD_SQUARE:
-      return field_box(p, 0.5, 0.5);
+      return field_box(p, parameters.x / 2.0, parameters.y / 2.0);
     case FIELD_TIME_DOMAIN:
       return field_time_domain(p);
     case FIELD_TOP:
       return field_top(p);
     case FIELD_WAVE:
-      return field_wave(p, 0.1);
+      return field_wave(p, parameters.x);
     case FIELD_X:
-      return field_x(p, 2.0, 0.25);
+      return field_x(p, parameters.x, parameters.y);
+    case FIELD_CUSTOM:
+      return field_custom(integers.x, p);
+    case FIELD_SPECTROGRAM:
+      return field_spectrogram(p, parameters.x);
     default:
       return field_none();
   }
 }
 
+// Evaluate the field, which is encoded as a sequence of instructions in
+// postfix order. Primitive fields push their distance onto the stack, and
+// combinators pop their operands and push the result. Transforms are
+// bracketed by push and pop instructions, which save and restore the
+// position.
+float distance_field(vec2 p) {
+  float stack[MAX_FIELD_STACK];
+  int top = 0;
+
+  vec2 positions[MAX_FIELD_TRANSFORMS];
+  int depth = 0;
+
+  for (int i = 0; i < MAX_FIELD_INSTRUCTIONS; i++) {
+    if (i >= field_instruction_count) {
+      break;
+    }
+
+    int opcode = field_opcodes[i];
+
+    switch (opcode) {
+      case FIELD_UNION:
+        top--;
+        stack[top - 1] = min(stack[top - 1], stack[top]);
+        break;
+      case FIELD_INTERSECT:
+        top--;
+        stack[top - 1] = max(stack[top - 1], stack[top]);
+        break;
+      case FIELD_SUBTRACT:
+        top--;
+        stack[top - 1] = max(stack[top - 1], -stack[top]);
+        break;
+      case FIELD_SMOOTH_UNION:
+        top--;
+        stack[top - 1] = field_smooth_union(stack[top - 1], stack[top], field_parameters[i].x);
+        break;
+      case FIELD_PUSH_TRANSFORM:
+        positions[depth] = p;
+        depth++;
+        p = (field_transforms[int(field_integers[i].x)] * vec3(p, 1.0)).xy;
+        break;
+      case FIELD_POP_TRANSFORM:
+        depth--;
+        p = positions[depth];
+        break;
+      default:
+        stack[top] = field_primitive(opcode, field_parameters[i], field_integers[i], p);
+        top++;
+        break;
+    }
+  }
+
+  return stack[0];
+}
+
 void main() {
-  // Get fragment coordinates and transform to [-1, 1]
-  vec2 position = gl_FragCoord.xy / resolution * 2.0 - 1.0;
+  // Get fragment coordinates within the image and transform to
+  // [-extent, extent], where the longer axis of the image spans [-1, 1]
+  vec2 position = ((gl_FragCoord.xy + offset) / resolution * 2.0 - 1.0) * extent;
 
   // Transform position by position transform matrix
   vec2 transformed = (position_transform * vec3(position, 1.0)).xy;
 
-  // Wrap transformed position to be within [-1, 1] if enabled
-  vec2 wrapped = wrap
-    ? mod(transformed + 1.0, 2.0) - 1.0
-    : transformed;
+  // Wrap transformed position to be within [-extent, extent] if enabled
+  vec2 wrapped = wrap_position(transformed);
 
   // Sample color if in-bounds, otherwise use default color
-  vec3 input_color = coordinates ? vec3(quadrant(wrapped), 0.0)
-    : abs(wrapped.x) <= 1.0 && abs(wrapped.y) <= 1.0 ? texture(source, quadrant(wrapped)).rgb
+  vec3 input_color = coordinates ? vec3(quadrant(wrapped / extent), 0.0)
+    : all(lessThanEqual(abs(wrapped), extent)) ? sample_source(quadrant(wrapped / extent))
     : default_color;
 
   // Sample original color
-  vec3 original_color = texture(source, gl_FragCoord.xy / resolution).rgb;
+  vec3 original_color = texture(original, gl_FragCoord.xy / vec2(textureSize(original, 0))).rgb;
 
-  // Calculate position in pixel coordinates, [0, resolution)
-  uvec2 pixel_position = uvec2(quadrant(wrapped) * resolution);
+  // Sample mask brightness if a mask is set, otherwise leave alpha unmasked
+  float mask_value = masked
+    ? dot(texture(mask, gl_FragCoord.xy / vec2(textureSize(mask, 0))).rgb, vec3(1.0 / 3.0))
+    : 1.0;
 
-  // Convert color from [0,1] to [-1,-1]
-  vec3 color_vector = input_color * 2.0 - 1.0;
+  // Convert color to color space
+  vec3 color_vector = to_color_space(input_color);
 
   // Transform color vector using color transform
   vec4 transformed_color_vector = color_transform * vec4(color_vector, 1.0);
 
-  // Convert color back from [-1,-1] to [0,1]
-  vec3 transformed_color = octant(transformed_color_vector.xyz);
+  // Convert color back from color space
+  vec3 transformed_color = from_color_space(transformed_color_vector.xyz);
 
   // Get the signed distance from the field
-  float distance = distance_field(wrapped, pixel_position);
+  float distance = distance_field(wrapped);
+
+  // Calculate coverage from distance, ramping across the boundary if
+  // feathering is enabled, otherwise full if distance is negative
+  float coverage = feather > 0.0
+    ? clamp(0.5 - distance / feather, 0.0, 1.0)
+    : distance <= 0.0 ? 1.0 : 0.0;
+
+  // Add glow falling off with distance outside of the field if enabled
+  if (glow > 0.0 && distance > 0.0) {
+    coverage = max(coverage, exp(-distance / glow));
+  }
+
+  // Scale alpha by mask and coverage
+  float alpha = alpha * mask_value * coverage;
 
-  // Set alpha to zero if distance is negative
-  float alpha = distance <= 0.0 ? alpha : 0.0;
+  // Blend transformed color with original color
+  vec3 blended_color = blend_color(transformed_color, original_color);
 
   // Perform alpha blending
-  vec3 output_color_rgb = transformed_color * alpha + original_color * (1.0 - alpha);
+  vec3 output_color_rgb = blended_color * alpha + original_color * (1.0 - alpha);
 
-  // Extend output color with opaque alpha channel
-  output_color = vec4(output_color_rgb, 1.0);
+  // Clamp output color, since floating point buffers are not clamped, and
+  // extend with opaque alpha channel
+  output_color = vec4(clamp(output_color_rgb, 0.0, 1.0), 1.0);
 }
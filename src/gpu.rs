@@ -1,24 +1,42 @@
 use super::*;
 
+// Once tiled exports are enabled, filters rendered since the last clear are
+// recorded so that they can be replayed, up to this limit. Filters whose
+// fields use audio share the audio data of the frame they were rendered on,
+// which is recorded once per frame and channel.
+const MAX_HISTORY: usize = 65536;
+
+// Maximum size of the buffers used for tiled exports
+const TILE_SIZE: u32 = 4096;
+
+// Time domain and frequency data read from an analyser
+type AudioData = (Vec<f32>, Vec<f32>);
+
 pub(crate) struct Gpu {
-  analyser_node: AnalyserNode,
-  audio_frequency_array: Float32Array,
-  audio_frequency_data: Vec<f32>,
-  audio_frequency_texture: WebGlTexture,
-  audio_time_domain_array: Float32Array,
-  audio_time_domain_data: Vec<f32>,
-  audio_time_domain_texture: WebGlTexture,
+  analyser_nodes: [AnalyserNode; 3],
+  analysers: [Analyser; 3],
+  aspect: bool,
+  buffers: BTreeMap<String, Buffer>,
   canvas: HtmlCanvasElement,
   decibels_max: f32,
   decibels_min: f32,
-  destination: WebGlTexture,
+  float_buffers: bool,
   frame_buffer: WebGlFramebuffer,
   gl: WebGl2RenderingContext,
+  half_float_buffers: bool,
   height: u32,
+  history: Option<Vec<(Filter, Option<Rc<AudioData>>)>>,
+  history_audio: [Option<Rc<AudioData>>; 3],
+  history_overflowed: bool,
+  images: BTreeMap<String, SourceImage>,
   lock_resolution: bool,
-  resolution: u32,
-  source: WebGlTexture,
-  uniforms: BTreeMap<String, WebGlUniformLocation>,
+  offline_audio: Option<[(Vec<f32>, Vec<f32>); 3]>,
+  precision: Precision,
+  present_program: Program,
+  presented: String,
+  programs: ProgramCache,
+  tile: Option<Tile>,
+  vertex: WebGlShader,
   width: u32,
   window: Window,
 }
@@ -27,7 +45,7 @@ impl Gpu {
   pub(super) fn new(
     window: &Window,
     canvas: &HtmlCanvasElement,
-    analyser_node: &AnalyserNode,
+    analyser_nodes: &[AnalyserNode; 3],
   ) -> Result<Self> {
     let mut context_options = WebGlContextAttributes::new();
 
@@ -48,172 +66,60 @@ impl Gpu {
 
     gl.enable(WebGl2RenderingContext::CULL_FACE);
 
-    let program = {
-      let program = gl.create_program().ok_or("Failed to create program")?;
-
-      let vertex = gl
-        .create_shader(WebGl2RenderingContext::VERTEX_SHADER)
-        .ok_or("Failed to create shader")?;
-
-      gl.shader_source(&vertex, include_str!("vertex.glsl"));
-      gl.compile_shader(&vertex);
-
-      if !gl.get_shader_parameter(&vertex, WebGl2RenderingContext::COMPILE_STATUS) {
-        return Err(
-          gl.get_shader_info_log(&vertex)
-            .ok_or("Failed to get shader info log")?
-            .into(),
-        );
-      }
-
-      let fragment = gl
-        .create_shader(WebGl2RenderingContext::FRAGMENT_SHADER)
-        .ok_or("Failed to create shader")?;
-
-      gl.shader_source(&fragment, include_str!("fragment.glsl"));
-      gl.compile_shader(&fragment);
-
-      if !gl.get_shader_parameter(&fragment, WebGl2RenderingContext::COMPILE_STATUS) {
-        return Err(
-          gl.get_shader_info_log(&fragment)
-            .ok_or("Failed to get shader info log")?
-            .into(),
-        );
-      }
-
-      gl.attach_shader(&program, &vertex);
-      gl.attach_shader(&program, &fragment);
-
-      gl.link_program(&program);
-
-      if !gl.get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS) {
-        return Err(
-          gl.get_program_info_log(&program)
-            .ok_or("Failed to get program log info")?
-            .into(),
-        );
-      }
-
-      gl.use_program(Some(&program));
-
-      program
-    };
-
     let width = canvas.width();
     let height = canvas.height();
-    let resolution = width.max(height);
 
     let frame_buffer = gl
       .create_framebuffer()
       .ok_or("Failed to create framebuffer")?;
 
-    let uniform_count = gl
-      .get_program_parameter(&program, WebGl2RenderingContext::ACTIVE_UNIFORMS)
-      .cast::<js_sys::Number>()?
-      .value_of() as u32;
-
-    let uniforms = (0..uniform_count)
-      .map(|i| {
-        let info = gl.get_active_uniform(&program, i).unwrap();
-        let name = info.name();
-        let location = gl.get_uniform_location(&program, &name).unwrap();
-        (name, location)
-      })
-      .collect::<BTreeMap<String, WebGlUniformLocation>>();
-
-    gl.uniform1i(Some(uniforms.get("source").unwrap()), 0);
-    gl.uniform1i(Some(uniforms.get("audio_time_domain").unwrap()), 1);
-    gl.uniform1i(Some(uniforms.get("audio_frequency").unwrap()), 2);
-
-    let audio_time_domain_texture = gl
-      .create_texture()
-      .ok_or("Failed to create audio_time_domain texture")?;
-
-    gl.active_texture(WebGl2RenderingContext::TEXTURE1);
-    gl.bind_texture(
-      WebGl2RenderingContext::TEXTURE_2D,
-      Some(&audio_time_domain_texture),
-    );
+    let vertex = Program::vertex(&gl)?;
 
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
+    let programs = ProgramCache::new(&gl, &vertex)?;
 
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
+    let present_program = Program::present(&gl, &vertex)?;
 
-    let fft_size = analyser_node.fft_size();
+    gl.use_program(Some(programs.current().program()));
 
-    gl.tex_storage_2d(
-      WebGl2RenderingContext::TEXTURE_2D,
-      1,
-      WebGl2RenderingContext::R32F,
-      fft_size as i32,
-      1,
-    );
+    let float_buffers = gl.get_extension("EXT_color_buffer_float")?.is_some();
 
-    let audio_frequency_texture = gl
-      .create_texture()
-      .ok_or("Failed to create audio_frequency texture")?;
+    let half_float_buffers = gl.get_extension("EXT_color_buffer_half_float")?.is_some();
 
-    gl.active_texture(WebGl2RenderingContext::TEXTURE2);
-    gl.bind_texture(
-      WebGl2RenderingContext::TEXTURE_2D,
-      Some(&audio_frequency_texture),
-    );
-
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
-
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
-
-    let frequency_bin_count = analyser_node.frequency_bin_count();
-
-    gl.tex_storage_2d(
-      WebGl2RenderingContext::TEXTURE_2D,
-      1,
-      WebGl2RenderingContext::R32F,
-      frequency_bin_count as i32,
-      1,
-    );
+    let analysers = Self::create_analysers(&gl, analyser_nodes)?;
 
     Ok(Self {
-      source: Self::create_texture(&gl, resolution)?,
-      destination: Self::create_texture(&gl, resolution)?,
-      analyser_node: analyser_node.clone(),
-      audio_time_domain_array: Float32Array::new_with_length(fft_size),
-      audio_time_domain_data: vec![0.0; fft_size as usize],
-      audio_time_domain_texture,
-      audio_frequency_array: Float32Array::new_with_length(frequency_bin_count),
-      audio_frequency_data: vec![0.0; frequency_bin_count as usize],
-      audio_frequency_texture,
+      analyser_nodes: analyser_nodes.clone(),
+      analysers,
+      aspect: false,
+      buffers: BTreeMap::new(),
       canvas: canvas.clone(),
       decibels_min: -100.0,
       decibels_max: -30.0,
+      float_buffers,
       frame_buffer,
       gl,
+      half_float_buffers,
       height,
+      history: None,
+      history_audio: Default::default(),
+      history_overflowed: false,
+      images: BTreeMap::new(),
       lock_resolution: false,
-      resolution,
-      uniforms,
+      offline_audio: None,
+      precision: Precision::Rgba8,
+      present_program,
+      presented: "default".into(),
+      programs,
+      tile: None,
+      vertex,
       width,
       window: window.clone(),
     })
   }
 
-  pub(crate) fn present(&self) -> Result {
+  pub(crate) fn present(&mut self) -> Result {
+    self.create_buffer(&self.presented.clone())?;
+
     self.gl.bind_framebuffer(
       WebGl2RenderingContext::READ_FRAMEBUFFER,
       Some(&self.frame_buffer),
@@ -227,16 +133,45 @@ impl Gpu {
       WebGl2RenderingContext::READ_FRAMEBUFFER,
       WebGl2RenderingContext::COLOR_ATTACHMENT0,
       WebGl2RenderingContext::TEXTURE_2D,
-      Some(&self.source),
+      Some(&self.buffers[&self.presented].source),
       0,
     );
 
     let width = self.width as i32;
     let height = self.height as i32;
-    let resolution = self.resolution as i32;
+    let (buffer_width, buffer_height) = self.buffer_size();
+
+    let dx = (buffer_width as i32 - width) / 2;
+    let dy = (buffer_height as i32 - height) / 2;
+
+    // Floating point buffers can't be blitted to the canvas, so draw them
+    if self.precision != Precision::Rgba8 {
+      self
+        .gl
+        .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+
+      self.gl.use_program(Some(self.present_program.program()));
+
+      self.gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+      self.gl.bind_texture(
+        WebGl2RenderingContext::TEXTURE_2D,
+        Some(&self.buffers[&self.presented].source),
+      );
+
+      self
+        .gl
+        .uniform2i(Some(self.present_program.uniform("offset")), dx, dy);
+
+      self.gl.viewport(0, 0, width, height);
+      self.gl.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, 3);
+      self
+        .gl
+        .viewport(0, 0, buffer_width as i32, buffer_height as i32);
+
+      self.gl.use_program(Some(self.programs.current().program()));
 
-    let dx = (resolution - width) / 2;
-    let dy = (resolution - height) / 2;
+      return Ok(());
+    }
 
     self.gl.blit_framebuffer(
       dx,
@@ -257,6 +192,73 @@ impl Gpu {
   pub(crate) fn render(&mut self, filter: &Filter) -> Result {
     self.resize()?;
 
+    let field = FieldProgram::new(&filter.field)?;
+
+    self.draw(filter, &field, None)?;
+
+    if let Some(history) = &mut self.history {
+      if history.len() == MAX_HISTORY {
+        self.history_overflowed = true;
+      } else if !self.history_overflowed {
+        let audio = if field.audio {
+          let channel = filter.audio_channel as usize;
+          let analyser = &self.analysers[channel];
+
+          Some(
+            self.history_audio[channel]
+              .get_or_insert_with(|| {
+                Rc::new((
+                  analyser.time_domain_data().to_vec(),
+                  analyser.frequency_data().to_vec(),
+                ))
+              })
+              .clone(),
+          )
+        } else {
+          None
+        };
+
+        history.push((filter.clone(), audio));
+      }
+    }
+
+    Ok(())
+  }
+
+  // Draw `filter`, using `audio` instead of reading the analyser, if given
+  fn draw(&mut self, filter: &Filter, field: &FieldProgram, audio: Option<&AudioData>) -> Result {
+    self
+      .programs
+      .use_program(&self.gl, &self.vertex, &field.sources)?;
+
+    self.create_buffer(&filter.source)?;
+    self.create_buffer(&filter.destination)?;
+
+    if let Some(mask) = &filter.mask {
+      self.create_buffer(mask)?;
+    }
+
+    let (width, height) = self.image_size();
+    let resolution = width.max(height) as f32;
+
+    let (x, y) = self.tile.map(|tile| tile.offset()).unwrap_or_default();
+
+    self
+      .gl
+      .uniform2f(Some(self.uniform("offset")), x as f32, y as f32);
+
+    self.gl.uniform2f(
+      Some(self.uniform("resolution")),
+      width as f32,
+      height as f32,
+    );
+
+    self.gl.uniform2f(
+      Some(self.uniform("extent")),
+      width as f32 / resolution,
+      height as f32 / resolution,
+    );
+
     for _ in 0..filter.times {
       self.gl.bind_framebuffer(
         WebGl2RenderingContext::FRAMEBUFFER,
@@ -267,73 +269,68 @@ impl Gpu {
         WebGl2RenderingContext::FRAMEBUFFER,
         WebGl2RenderingContext::COLOR_ATTACHMENT0,
         WebGl2RenderingContext::TEXTURE_2D,
-        Some(&self.destination),
+        Some(&self.buffers[&filter.destination].destination),
         0,
       );
 
       self.gl.active_texture(WebGl2RenderingContext::TEXTURE0);
-      self
-        .gl
-        .bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&self.source));
+      self.gl.bind_texture(
+        WebGl2RenderingContext::TEXTURE_2D,
+        Some(&self.buffers[&filter.source].source),
+      );
 
-      self
-        .analyser_node
-        .get_float_time_domain_data(&mut self.audio_time_domain_data);
-      self
-        .audio_time_domain_array
-        .copy_from(&self.audio_time_domain_data);
-      self.gl.active_texture(WebGl2RenderingContext::TEXTURE1);
+      self.gl.active_texture(WebGl2RenderingContext::TEXTURE3);
       self.gl.bind_texture(
         WebGl2RenderingContext::TEXTURE_2D,
-        Some(&self.audio_time_domain_texture),
+        Some(&self.buffers[&filter.destination].source),
       );
+
+      // Unbind the mask when there isn't one, since a mask left over from a
+      // previous render may now be attached to the framebuffer
+      self.gl.active_texture(WebGl2RenderingContext::TEXTURE4);
+      self.gl.bind_texture(
+        WebGl2RenderingContext::TEXTURE_2D,
+        filter.mask.as_ref().map(|mask| &self.buffers[mask].source),
+      );
+
       self
         .gl
-        .tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
-          WebGl2RenderingContext::TEXTURE_2D,
-          0,
-          0,
-          0,
-          self.audio_time_domain_data.len() as i32,
-          1,
-          WebGl2RenderingContext::RED,
-          WebGl2RenderingContext::FLOAT,
-          Some(&self.audio_time_domain_array),
-        )?;
+        .uniform1ui(Some(self.uniform("masked")), filter.mask.is_some() as u32);
+
+      let channel = filter.audio_channel as usize;
+
+      match audio {
+        Some((time_domain, frequency)) => {
+          self.analysers[channel].restore(time_domain, frequency)?;
+        }
+        None => self.analysers[channel].read(
+          self
+            .offline_audio
+            .as_ref()
+            .map(|offline_audio| &offline_audio[channel]),
+          self.decibels_min,
+          self.decibels_max,
+        ),
+      }
 
-      self
-        .analyser_node
-        .get_float_frequency_data(&mut self.audio_frequency_data);
+      self.analysers[channel].bind(&self.gl)?;
 
-      let scale_factor = 1.0 / (self.decibels_max - self.decibels_min);
+      self.gl.uniform1i(
+        Some(self.uniform("audio_spectrogram_row")),
+        self.analysers[channel].spectrogram_row().try_into()?,
+      );
 
-      for bucket in &mut self.audio_frequency_data {
-        *bucket = ((*bucket - self.decibels_min) * scale_factor).clamp(0.0, 1.0);
-      }
+      self.gl.uniform1f(Some(self.uniform("alpha")), filter.alpha);
 
-      self
-        .audio_frequency_array
-        .copy_from(&self.audio_frequency_data);
-      self.gl.active_texture(WebGl2RenderingContext::TEXTURE2);
-      self.gl.bind_texture(
-        WebGl2RenderingContext::TEXTURE_2D,
-        Some(&self.audio_frequency_texture),
-      );
       self
         .gl
-        .tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
-          WebGl2RenderingContext::TEXTURE_2D,
-          0,
-          0,
-          0,
-          self.audio_frequency_data.len() as i32,
-          1,
-          WebGl2RenderingContext::RED,
-          WebGl2RenderingContext::FLOAT,
-          Some(&self.audio_frequency_array),
-        )?;
+        .uniform1f(Some(self.uniform("feather")), filter.feather);
 
-      self.gl.uniform1f(Some(self.uniform("alpha")), filter.alpha);
+      self.gl.uniform1f(Some(self.uniform("glow")), filter.glow);
+
+      self
+        .gl
+        .uniform1i(Some(self.uniform("blend")), filter.blend as i32);
 
       self.gl.uniform3f(
         Some(self.uniform("default_color")),
@@ -342,6 +339,10 @@ impl Gpu {
         filter.default_color[2],
       );
 
+      self
+        .gl
+        .uniform1i(Some(self.uniform("color_space")), filter.color_space as i32);
+
       self.gl.uniform_matrix4fv_with_f32_array(
         Some(self.uniform("color_transform")),
         false,
@@ -356,94 +357,170 @@ impl Gpu {
 
       self
         .gl
-        .uniform1ui(Some(self.uniform("wrap")), filter.wrap as u32);
+        .uniform1i(Some(self.uniform("sampling")), filter.sampling as i32);
+
+      self
+        .gl
+        .uniform1i(Some(self.uniform("wrap")), filter.wrap as i32);
 
       self.gl.uniform1i(
-        Some(self.uniform("field")),
-        match filter.field {
-          Field::All => 0,
-          Field::Check => 1,
-          Field::Circle => 2,
-          Field::Cross => 3,
-          Field::Equalizer => 4,
-          Field::Frequency => 5,
-          Field::Mod { divisor, remainder } => {
-            self
-              .gl
-              .uniform1ui(Some(self.uniform("mod_divisor")), divisor);
-            self
-              .gl
-              .uniform1ui(Some(self.uniform("mod_remainder")), remainder);
-            6
-          }
-          Field::Rows { on, off } => {
-            self.gl.uniform1ui(Some(self.uniform("rows_on")), on);
-            self.gl.uniform1ui(Some(self.uniform("rows_off")), off);
-            7
-          }
-          Field::Square => 8,
-          Field::TimeDomain => 9,
-          Field::Top => 10,
-          Field::Wave => 11,
-          Field::X => 12,
-        },
+        Some(self.uniform("field_instruction_count")),
+        field.opcodes.len().try_into()?,
       );
 
+      self
+        .gl
+        .uniform1iv_with_i32_array(Some(self.uniform("field_opcodes[0]")), &field.opcodes);
+
+      self
+        .gl
+        .uniform4fv_with_f32_array(Some(self.uniform("field_parameters[0]")), &field.parameters);
+
+      self
+        .gl
+        .uniform2uiv_with_u32_array(Some(self.uniform("field_integers[0]")), &field.integers);
+
+      if !field.transforms.is_empty() {
+        self.gl.uniform_matrix3fv_with_f32_array(
+          Some(self.uniform("field_transforms[0]")),
+          false,
+          &field.transforms,
+        );
+      }
+
       self
         .gl
         .uniform1ui(Some(self.uniform("coordinates")), filter.coordinates as u32);
 
       self.gl.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, 3);
 
-      mem::swap(&mut self.source, &mut self.destination);
+      self
+        .buffers
+        .get_mut(&filter.destination)
+        .ok_or("Failed to retrieve destination buffer")?
+        .swap();
     }
 
     Ok(())
   }
 
-  fn create_texture(gl: &WebGl2RenderingContext, resolution: u32) -> Result<WebGlTexture> {
-    let texture = gl.create_texture().ok_or("Failed to create texture")?;
+  fn create_buffer(&mut self, name: &str) -> Result {
+    if !self.buffers.contains_key(name) {
+      let (width, height) = self.buffer_size();
+      let buffer = Buffer::new(&self.gl, width, height, self.precision)?;
 
-    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+      if let Some(image) = self.images.get(name) {
+        let (size, offset) = self.image_region();
+        image.draw(&self.gl, &buffer.source, self.precision, size, offset)?;
+      }
 
-    gl.tex_storage_2d(
-      WebGl2RenderingContext::TEXTURE_2D,
-      1,
-      WebGl2RenderingContext::RGBA8,
-      resolution as i32,
-      resolution as i32,
-    );
+      self.buffers.insert(name.into(), buffer);
+    }
 
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
+    Ok(())
+  }
 
-    gl.tex_parameteri(
-      WebGl2RenderingContext::TEXTURE_2D,
-      WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-      WebGl2RenderingContext::NEAREST.try_into()?,
-    );
+  pub(crate) fn load_image(&mut self, name: &str, image: SourceImage) -> Result {
+    if let Some(buffer) = self.buffers.get(name) {
+      let (size, offset) = self.image_region();
+      image.draw(&self.gl, &buffer.source, self.precision, size, offset)?;
+    }
+
+    self.images.insert(name.into(), image);
+
+    Ok(())
+  }
+
+  pub(crate) fn draw_camera(&mut self, camera: &mut Camera) -> Result {
+    self.create_buffer(&camera.buffer)?;
+
+    let (size, offset) = self.image_region();
+
+    camera.draw(
+      &self.gl,
+      &self.buffers[&camera.buffer].source,
+      self.precision,
+      size,
+      offset,
+    )
+  }
 
-    Ok(texture)
+  pub(crate) fn stop_camera(&self, camera: Camera) -> Result {
+    camera.stop(&self.gl)
   }
 
-  pub(crate) fn lock_resolution(&mut self, resolution: u32) {
-    self.width = resolution;
-    self.height = resolution;
-    self.resolution = resolution;
+  pub(crate) fn clear_images(&mut self) {
+    self.images.clear();
+  }
+
+  pub(crate) fn set_presented(&mut self, name: &str) {
+    self.presented = name.into();
+  }
+
+  // Buffers match the canvas if aspect-aware rendering is enabled, and are
+  // otherwise square, with the canvas showing a center crop
+  fn buffer_size(&self) -> (u32, u32) {
+    if let Some(tile) = self.tile {
+      tile.buffer_size()
+    } else if self.aspect {
+      (self.width, self.height)
+    } else {
+      let resolution = self.width.max(self.height);
+      (resolution, resolution)
+    }
+  }
+
+  // Size of the image being rendered, which is larger than the buffers when
+  // rendering a tile
+  fn image_size(&self) -> (u32, u32) {
+    match self.tile {
+      Some(tile) => (tile.image_width, tile.image_height),
+      None => self.buffer_size(),
+    }
+  }
+
+  // Size of the region that loaded images are placed within, and the position
+  // of the bottom left corner of the buffers within that region. This is the
+  // visible region of the canvas, or the whole image when rendering a tile.
+  fn image_region(&self) -> ((u32, u32), (i32, i32)) {
+    match self.tile {
+      Some(tile) => ((tile.image_width, tile.image_height), tile.offset()),
+      None => {
+        let (buffer_width, buffer_height) = self.buffer_size();
+        (
+          (self.width, self.height),
+          (
+            -((buffer_width - self.width) as i32 / 2),
+            -((buffer_height - self.height) as i32 / 2),
+          ),
+        )
+      }
+    }
+  }
+
+  pub(crate) fn lock_resolution(&mut self, width: u32, height: u32) {
+    self.width = width;
+    self.height = height;
     self.lock_resolution = true;
   }
 
+  pub(crate) fn set_aspect(&mut self, aspect: bool) -> Result {
+    if aspect != self.aspect {
+      self.aspect = aspect;
+      self.configure()?;
+    }
+
+    Ok(())
+  }
+
   pub(crate) fn resize(&mut self) -> Result {
     if self.lock_resolution {
       if self.canvas.height() == self.height && self.canvas.width() == self.width {
         return Ok(());
       }
     } else {
-      let css_pixel_height: f64 = self.canvas.client_height().try_into()?;
-      let css_pixel_width: f64 = self.canvas.client_width().try_into()?;
+      let css_pixel_height: f64 = self.canvas.client_height().into();
+      let css_pixel_width: f64 = self.canvas.client_width().into();
 
       let device_pixel_ratio = self.window.device_pixel_ratio();
       let device_pixel_height = (css_pixel_height * device_pixel_ratio).ceil() as u32;
@@ -455,61 +532,330 @@ impl Gpu {
 
       self.width = device_pixel_width;
       self.height = device_pixel_height;
-      self.resolution = self.width.max(self.height);
     }
 
     self.canvas.set_height(self.height);
     self.canvas.set_width(self.width);
 
-    self
+    self.configure()
+  }
+
+  fn configure(&mut self) -> Result {
+    let (width, height) = self.buffer_size();
+
+    self.gl.viewport(0, 0, width as i32, height as i32);
+
+    self.clear()?;
+
+    self.present()?;
+
+    Ok(())
+  }
+
+  pub(crate) fn save_image(&mut self) -> Result<DynamicImage> {
+    self.create_buffer(&self.presented.clone())?;
+
+    // Save the visible region of the buffer
+    let (buffer_width, buffer_height) = self.buffer_size();
+    let x = (buffer_width - self.width) / 2;
+    let y = (buffer_height - self.height) / 2;
+
+    self.read_image(x, y, self.width, self.height)
+  }
+
+  // Start recording rendered filters for tiled exports
+  pub(crate) fn record_tiled(&mut self) {
+    self.history.get_or_insert_with(Vec::new);
+  }
+
+  // Replay the filters rendered since the last clear at `width` by `height`,
+  // one tile at a time, and stitch the presented buffer of each tile into a
+  // single image. This allows saving images larger than the maximum texture
+  // size. The camera's current frame is drawn into its buffer at the start of
+  // each tile.
+  pub(crate) fn save_tiled(
+    &mut self,
+    width: u32,
+    height: u32,
+    tile_size: Option<u32>,
+    camera: Option<&mut Camera>,
+  ) -> Result<DynamicImage> {
+    if self.history.is_none() {
+      return Err(
+        "`recordTiled()` must be called before rendering filters to be saved with `saveTiled()`"
+          .into(),
+      );
+    }
+
+    if self.history_overflowed {
+      return Err(
+        format!(
+          "More than {MAX_HISTORY} filters have been rendered since the last clear, so they cannot be replayed"
+        )
+        .into(),
+      );
+    }
+
+    let max_texture_size = self
       .gl
-      .uniform1f(Some(self.uniform("resolution")), self.resolution as f32);
+      .get_parameter(WebGl2RenderingContext::MAX_TEXTURE_SIZE)?
+      .as_f64()
+      .ok_or("Failed to get maximum texture size")? as u32;
+
+    let padding = Tile::padding(
+      self.history.iter().flatten().map(|(filter, _)| filter),
+      width,
+      height,
+    )?;
 
+    let tiles = Tile::split(
+      width,
+      height,
+      tile_size.unwrap_or(TILE_SIZE).min(max_texture_size),
+      padding,
+    )?;
+
+    let mut image = if self.precision == Precision::Rgba8 {
+      DynamicImage::new_rgba8(width, height)
+    } else {
+      DynamicImage::new_rgba16(width, height)
+    };
+
+    let buffers = mem::take(&mut self.buffers);
+    let history = mem::take(&mut self.history);
+
+    // Replaying overwrites the analysers' data with recorded audio
+    let live = self.analysers.each_ref().map(|analyser| {
+      (
+        analyser.time_domain_data().to_vec(),
+        analyser.frequency_data().to_vec(),
+      )
+    });
+
+    let result = self.render_tiles(&tiles, history.iter().flatten(), camera, &mut image);
+
+    self.tile = None;
+    self.buffers = buffers;
+    self.history = history;
+
+    for (analyser, (time_domain, frequency)) in self.analysers.iter_mut().zip(&live) {
+      analyser.restore(time_domain, frequency)?;
+    }
+
+    let (buffer_width, buffer_height) = self.buffer_size();
     self
       .gl
-      .viewport(0, 0, self.resolution as i32, self.resolution as i32);
+      .viewport(0, 0, buffer_width as i32, buffer_height as i32);
 
-    self.clear()?;
+    result?;
 
-    self.present()?;
+    Ok(image)
+  }
+
+  fn render_tiles<'a>(
+    &mut self,
+    tiles: &[Tile],
+    history: impl Iterator<Item = &'a (Filter, Option<Rc<AudioData>>)> + Clone,
+    mut camera: Option<&mut Camera>,
+    image: &mut DynamicImage,
+  ) -> Result {
+    for tile in tiles {
+      self.tile = Some(*tile);
+
+      let (width, height) = tile.buffer_size();
+      self.gl.viewport(0, 0, width as i32, height as i32);
+
+      let result = camera
+        .as_deref_mut()
+        .map_or(Ok(()), |camera| self.draw_camera(camera))
+        .and_then(|()| {
+          history.clone().try_for_each(|(filter, audio)| {
+            self.draw(filter, &FieldProgram::new(&filter.field)?, audio.as_deref())
+          })
+        })
+        .and_then(|()| self.create_buffer(&self.presented.clone()))
+        .and_then(|()| self.read_image(tile.padding, tile.padding, tile.width, tile.height));
+
+      for buffer in self.buffers.values() {
+        buffer.delete(&self.gl);
+      }
+
+      self.buffers.clear();
+
+      // Tiles are positioned from the bottom, images from the top
+      image.copy_from(&result?, tile.x, tile.image_height - tile.y - tile.height)?;
+    }
 
     Ok(())
   }
 
-  pub(crate) fn save_image(&self) -> Result<ImageBuffer<image::Rgba<u8>, Vec<u8>>> {
+  // Read a region of the presented buffer into an image. Floating point
+  // buffers are read with 16 bits per channel.
+  fn read_image(&self, x: u32, y: u32, width: u32, height: u32) -> Result<DynamicImage> {
     self.gl.bind_framebuffer(
       WebGl2RenderingContext::FRAMEBUFFER,
       Some(&self.frame_buffer),
     );
 
-    let mut array = vec![0; (self.resolution * self.resolution * 4) as usize];
-    self.gl.read_pixels_with_opt_u8_array(
-      0,
+    self.gl.framebuffer_texture_2d(
+      WebGl2RenderingContext::FRAMEBUFFER,
+      WebGl2RenderingContext::COLOR_ATTACHMENT0,
+      WebGl2RenderingContext::TEXTURE_2D,
+      Some(&self.buffers[&self.presented].source),
       0,
-      self.resolution as i32,
-      self.resolution as i32,
-      WebGl2RenderingContext::RGBA,
-      WebGl2RenderingContext::UNSIGNED_BYTE,
-      Some(&mut array),
-    )?;
+    );
+
+    let len = (width * height * 4) as usize;
+
+    let mut image = if self.precision == Precision::Rgba8 {
+      let mut array = vec![0; len];
+      self.gl.read_pixels_with_opt_u8_array(
+        x as i32,
+        y as i32,
+        width as i32,
+        height as i32,
+        WebGl2RenderingContext::RGBA,
+        WebGl2RenderingContext::UNSIGNED_BYTE,
+        Some(&mut array),
+      )?;
+
+      DynamicImage::ImageRgba8(
+        ImageBuffer::from_raw(width, height, array).ok_or("Failed to create ImageBuffer")?,
+      )
+    } else {
+      let array = Float32Array::new_with_length(len.try_into()?);
+      self.gl.read_pixels_with_opt_array_buffer_view(
+        x as i32,
+        y as i32,
+        width as i32,
+        height as i32,
+        WebGl2RenderingContext::RGBA,
+        WebGl2RenderingContext::FLOAT,
+        Some(&array),
+      )?;
+
+      DynamicImage::ImageRgba16(
+        ImageBuffer::from_raw(
+          width,
+          height,
+          array
+            .to_vec()
+            .into_iter()
+            .map(|n| (n.clamp(0.0, 1.0) * 65535.0).round() as u16)
+            .collect(),
+        )
+        .ok_or("Failed to create ImageBuffer")?,
+      )
+    };
 
-    let image = ImageBuffer::from_raw(self.resolution, self.resolution, array)
-      .ok_or("Failed to create ImageBuffer")?;
+    // `read_pixels` returns rows from bottom to top
+    image = image.flipv();
 
     Ok(image)
   }
 
+  pub(crate) fn set_precision(&mut self, precision: Precision) -> Result {
+    let precision = match precision {
+      Precision::Rgba8 => precision,
+      Precision::Rgba16f => {
+        if !self.float_buffers && !self.half_float_buffers {
+          return Err("Half precision floating point buffers are not supported".into());
+        }
+        precision
+      }
+      Precision::Rgba32f => {
+        if self.float_buffers {
+          precision
+        } else if self.half_float_buffers {
+          log::warn!(
+            "Single precision floating point buffers are not supported, using half precision"
+          );
+          Precision::Rgba16f
+        } else {
+          return Err("Floating point buffers are not supported".into());
+        }
+      }
+    };
+
+    if precision != self.precision {
+      self.precision = precision;
+      self.clear()?;
+      self.present()?;
+    }
+
+    Ok(())
+  }
+
+  // Use offline time domain and frequency data instead of reading from the
+  // analyser, or read from the analyser again if `None`
+  pub(crate) fn set_offline_audio(&mut self, offline_audio: Option<[(Vec<f32>, Vec<f32>); 3]>) {
+    self.offline_audio = offline_audio;
+  }
+
+  // Read audio data for every channel and add it to the spectrograms, once per
+  // animation frame
+  pub(crate) fn analyze(&mut self) -> Result {
+    self.history_audio = Default::default();
+
+    for (channel, analyser) in self.analysers.iter_mut().enumerate() {
+      analyser.read(
+        self
+          .offline_audio
+          .as_ref()
+          .map(|offline_audio| &offline_audio[channel]),
+        self.decibels_min,
+        self.decibels_max,
+      );
+      analyser.push_spectrogram(&self.gl)?;
+    }
+
+    Ok(())
+  }
+
+  pub(crate) fn audio_summary(&self, sample_rate: f32, bins: bool) -> AudioSummary {
+    let analyser = &self.analysers[AudioChannel::Mix as usize];
+
+    AudioSummary::new(
+      analyser.time_domain_data(),
+      analyser.decibels(),
+      analyser.frequency_data(),
+      sample_rate,
+      bins,
+    )
+  }
+
+  pub(crate) fn audio_frequency_data(&self) -> &[f32] {
+    self.analysers[AudioChannel::Mix as usize].frequency_data()
+  }
+
+  fn create_analysers(
+    gl: &WebGl2RenderingContext,
+    analyser_nodes: &[AnalyserNode; 3],
+  ) -> Result<[Analyser; 3]> {
+    Ok([
+      Analyser::new(gl, &analyser_nodes[0])?,
+      Analyser::new(gl, &analyser_nodes[1])?,
+      Analyser::new(gl, &analyser_nodes[2])?,
+    ])
+  }
+
+  // Reallocate audio textures after the analysers' FFT size changes. Offline
+  // audio data, and audio data recorded this frame, were computed for the old
+  // size, so they are discarded.
+  pub(crate) fn resize_analysers(&mut self) -> Result {
+    self.analysers = Self::create_analysers(&self.gl, &self.analyser_nodes)?;
+    self.offline_audio = None;
+    self.history_audio = Default::default();
+    Ok(())
+  }
+
   pub(crate) fn set_decibel_range(&mut self, min: f32, max: f32) {
     self.decibels_min = min;
     self.decibels_max = max;
   }
 
   fn uniform(&self, name: &str) -> &WebGlUniformLocation {
-    self
-      .uniforms
-      .get(name)
-      .ok_or_else(|| format!("Uniform `{name}` is missing.",))
-      .unwrap()
+    self.programs.current().uniform(name)
   }
 
   pub(crate) fn clear(&mut self) -> Result {
@@ -517,11 +863,17 @@ impl Gpu {
       .gl
       .clear_bufferfv_with_f32_array(WebGl2RenderingContext::COLOR, 0, &[0.0, 0.0, 0.0, 1.0]);
 
-    self.gl.delete_texture(Some(&self.source));
-    self.gl.delete_texture(Some(&self.destination));
+    for buffer in self.buffers.values() {
+      buffer.delete(&self.gl);
+    }
+
+    self.buffers.clear();
+
+    if let Some(history) = &mut self.history {
+      history.clear();
+    }
 
-    self.source = Self::create_texture(&self.gl, self.resolution)?;
-    self.destination = Self::create_texture(&self.gl, self.resolution)?;
+    self.history_overflowed = false;
 
     Ok(())
   }
@@ -1,11 +1,21 @@
 use {
-  serde::{Deserialize, Serialize},
-  std::cell::RefCell,
+  image::{Rgba, RgbaImage},
+  serde::{de, Deserialize, Deserializer, Serialize},
+  std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+  },
   wasm_bindgen::{closure::Closure, JsCast, JsValue},
   web_sys::{DedicatedWorkerGlobalScope, MessageEvent},
 };
 
-pub use std::f32::consts::TAU;
+pub use {
+  crate::cpu::{Cpu, CpuError},
+  std::f32::consts::TAU,
+};
+
+mod cpu;
 
 pub type Matrix3 = nalgebra::Matrix3<f32>;
 pub type Matrix4 = nalgebra::Matrix4<f32>;
@@ -15,6 +25,7 @@ pub type Scale2 = nalgebra::Scale2<f32>;
 pub type Similarity2 = nalgebra::Similarity2<f32>;
 pub type Similarity3 = nalgebra::Similarity3<f32>;
 pub type Translation2 = nalgebra::Translation2<f32>;
+pub type Vector2 = nalgebra::Vector2<f32>;
 pub type Vector3 = nalgebra::Vector3<f32>;
 
 thread_local! {
@@ -25,6 +36,69 @@ pub fn send(message: Message) {
   SYSTEM.with(|system| system.borrow_mut().send(message));
 }
 
+pub fn analyser(fft_size: u32, smoothing: f32) {
+  send(Message::Analyser {
+    fft_size,
+    smoothing,
+  });
+}
+
+pub fn aspect(aspect: bool) {
+  send(Message::Aspect(aspect));
+}
+
+pub fn audio(audio: &[u8]) {
+  send(Message::Audio(base64::encode(audio)));
+}
+
+pub fn audio_bins(enabled: bool) {
+  send(Message::AudioBins(enabled));
+}
+
+pub fn audio_summary() -> AudioSummary {
+  SYSTEM.with(|system| system.borrow().audio.clone())
+}
+
+pub fn camera(buffer: &str, placement: Placement) {
+  send(Message::Camera {
+    buffer: buffer.into(),
+    placement,
+  });
+}
+
+pub fn capture(frames: u32, fps: u32, format: CaptureFormat) {
+  send(Message::Capture {
+    frames,
+    fps,
+    format,
+  });
+}
+
+pub fn checkbox(name: &str) -> bool {
+  SYSTEM
+    .with(|system| system.borrow_mut().widget(name, Widget::Checkbox))
+    .and_then(|value| value.as_bool())
+    .unwrap_or(false)
+}
+
+pub fn clear() {
+  send(Message::Clear);
+}
+
+pub fn decibel_range(min: f32, max: f32) {
+  send(Message::DecibelRange { min, max });
+}
+
+pub fn done() {
+  SYSTEM.with(|system| {
+    let mut system = system.borrow_mut();
+    if !system.done {
+      system.done = true;
+      system.send(Message::Done);
+    }
+  });
+}
+
 pub fn error(message: impl ToString) {
   SYSTEM.with(|system| {
     system
@@ -33,11 +107,311 @@ pub fn error(message: impl ToString) {
   });
 }
 
+pub fn frame() -> Frame {
+  SYSTEM.with(|system| system.borrow().frame)
+}
+
+pub fn load_image(image: &[u8], placement: Placement, buffer: &str) {
+  send(Message::LoadImage {
+    image: base64::encode(image),
+    placement,
+    buffer: buffer.into(),
+  });
+}
+
+pub fn offline_audio(fps: Option<f64>) {
+  send(Message::OfflineAudio(fps));
+}
+
+pub fn oscillator_frequency(frequency: f32) {
+  send(Message::OscillatorFrequency(frequency));
+}
+
+pub fn oscillator_gain(gain: f32) {
+  send(Message::OscillatorGain(gain));
+}
+
+pub fn pause() {
+  send(Message::Pause);
+}
+
+pub fn play() {
+  send(Message::Play);
+}
+
+pub fn precision(precision: Precision) {
+  send(Message::Precision(precision));
+}
+
+pub fn present(buffer: &str) {
+  send(Message::Present(buffer.into()));
+}
+
+pub fn radio(name: &str, options: &[&str]) -> String {
+  SYSTEM
+    .with(|system| {
+      system.borrow_mut().widget(
+        name,
+        Widget::Radio {
+          options: options.iter().map(|option| option.to_string()).collect(),
+        },
+      )
+    })
+    .and_then(|value| value.as_str().map(str::to_string))
+    .or_else(|| options.first().map(|option| option.to_string()))
+    .unwrap_or_default()
+}
+
+pub fn record() {
+  send(Message::Record);
+}
+
+pub fn record_session() {
+  send(Message::RecordSession);
+}
+
+pub fn record_tiled() {
+  send(Message::RecordTiled);
+}
+
+pub fn resolution(width: u32, height: u32) {
+  send(Message::Resolution { width, height });
+}
+
+pub fn save() {
+  send(Message::Save);
+}
+
+pub fn save_session() {
+  send(Message::SaveSession);
+}
+
+pub fn save_tiled(width: u32, height: u32, tile_size: Option<u32>) {
+  send(Message::SaveTiled {
+    width,
+    height,
+    tile_size,
+  });
+}
+
+pub fn seek(time: f64) {
+  send(Message::Seek(time));
+}
+
+pub fn slider(name: &str, min: f64, max: f64, step: f64, initial: f64) -> f64 {
+  SYSTEM
+    .with(|system| {
+      system.borrow_mut().widget(
+        name,
+        Widget::Slider {
+          initial,
+          max,
+          min,
+          step,
+        },
+      )
+    })
+    .and_then(|value| value.as_f64())
+    .unwrap_or(initial)
+}
+
+pub fn tap_tempo(name: &str) {
+  SYSTEM.with(|system| system.borrow_mut().widget(name, Widget::TapTempo));
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioChannel {
+  #[default]
+  Mix,
+  Left,
+  Right,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Blend {
+  #[default]
+  Normal,
+  Add,
+  Multiply,
+  Screen,
+  Overlay,
+  Difference,
+  Lighten,
+  Darken,
+  Xor,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum CaptureFormat {
+  Apng,
+  Gif,
+  Zip,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Placement {
+  #[default]
+  Fit,
+  Fill,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Precision {
+  #[default]
+  Rgba8,
+  Rgba16f,
+  Rgba32f,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Sampling {
+  #[default]
+  Nearest,
+  Bilinear,
+  Bicubic,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Wrap {
+  #[default]
+  DefaultColor,
+  Repeat,
+  Mirror,
+  Clamp,
+}
+
+impl Wrap {
+  // Wrap was a boolean before it had modes, and booleans are still accepted,
+  // so that filters saved before then can still be loaded
+  fn deserialize_legacy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    match value.as_bool() {
+      Some(wrap) => Ok(wrap.into()),
+      None => Self::deserialize(value).map_err(de::Error::custom),
+    }
+  }
+}
+
+impl From<bool> for Wrap {
+  fn from(wrap: bool) -> Self {
+    if wrap {
+      Self::Repeat
+    } else {
+      Self::DefaultColor
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorSpace {
+  #[default]
+  Rgb,
+  Hsv,
+  Hsl,
+  Oklab,
+  Oklch,
+}
+
+// Summary of the audio analyzed on a frame. `bass`, `mid`, and `treble` are
+// the energies of each band, the sums of the squared linear magnitudes of its
+// frequency bins, and `centroid` is weighted by the linear magnitudes.
+// `frequency`, if requested, holds the bins as the audio fields see them,
+// normalized from decibels to [0, 1] over the decibel range.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioSummary {
+  pub bass: f32,
+  pub centroid: f32,
+  pub frequency: Option<Vec<f32>>,
+  pub mid: f32,
+  pub peak: f32,
+  pub rms: f32,
+  pub time_domain: Option<Vec<f32>>,
+  pub treble: f32,
+}
+
+impl AudioSummary {
+  const BASS: f32 = 250.0;
+  const TREBLE: f32 = 4000.0;
+
+  pub fn new(
+    time_domain: &[f32],
+    decibels: &[f32],
+    frequency: &[f32],
+    sample_rate: f32,
+    bins: bool,
+  ) -> Self {
+    let mut sum = 0.0;
+    let mut peak = 0.0f32;
+
+    for sample in time_domain {
+      sum += sample * sample;
+      peak = peak.max(sample.abs());
+    }
+
+    let rms = if time_domain.is_empty() {
+      0.0
+    } else {
+      (sum / time_domain.len() as f32).sqrt()
+    };
+
+    let bin_width = sample_rate / (decibels.len() * 2) as f32;
+
+    let mut bands = [0.0; 3];
+    let mut weighted = 0.0;
+    let mut total = 0.0;
+
+    for (i, decibels) in decibels.iter().enumerate() {
+      let hz = i as f32 * bin_width;
+      let magnitude = 10.0f32.powf(decibels / 20.0);
+
+      let band = if hz < Self::BASS {
+        0
+      } else if hz < Self::TREBLE {
+        1
+      } else {
+        2
+      };
+
+      bands[band] += magnitude * magnitude;
+
+      weighted += hz * magnitude;
+      total += magnitude;
+    }
+
+    let [bass, mid, treble] = bands;
+
+    Self {
+      bass,
+      centroid: if total > 0.0 { weighted / total } else { 0.0 },
+      frequency: bins.then(|| frequency.to_vec()),
+      mid,
+      peak,
+      rms,
+      time_domain: bins.then(|| time_domain.to_vec()),
+      treble,
+    }
+  }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "tag", content = "content")]
 pub enum Event {
-  Frame(f32),
+  Frame {
+    audio: AudioSummary,
+    tempo: Tempo,
+    time: f32,
+  },
   Script(String),
   Widget {
     key: String,
@@ -46,16 +420,27 @@ pub enum Event {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Filter {
   pub alpha: f32,
+  pub audio_channel: AudioChannel,
+  pub blend: Blend,
+  pub color_space: ColorSpace,
   pub color_transform: Matrix4,
   pub position_transform: Matrix3,
   pub coordinates: bool,
   pub default_color: [f32; 3],
+  pub destination: String,
+  pub feather: f32,
+  #[serde(deserialize_with = "Field::deserialize_legacy")]
   pub field: Field,
+  pub glow: f32,
+  pub mask: Option<String>,
+  pub source: String,
   pub times: u32,
-  pub wrap: bool,
+  pub sampling: Sampling,
+  #[serde(deserialize_with = "Wrap::deserialize_legacy")]
+  pub wrap: Wrap,
 }
 
 impl Filter {
@@ -64,22 +449,30 @@ impl Filter {
   }
 
   pub fn x(self) -> Self {
-    Self {
-      field: Field::X,
-      ..self
-    }
+    self.field(Field::X {
+      size: 2.0,
+      radius: 0.25,
+    })
   }
 
   pub fn circle(self) -> Self {
+    self.field(Field::Circle { radius: 1.0 })
+  }
+
+  pub fn field(self, field: Field) -> Self {
+    Self { field, ..self }
+  }
+
+  pub fn position(self, position_transform: impl Into<Matrix3>) -> Self {
     Self {
-      field: Field::Circle,
+      position_transform: position_transform.into(),
       ..self
     }
   }
 
-  pub fn position(self, position_transform: impl Into<Matrix3>) -> Self {
+  pub fn color_space(self, color_space: ColorSpace) -> Self {
     Self {
-      position_transform: position_transform.into(),
+      color_space,
       ..self
     }
   }
@@ -95,8 +488,55 @@ impl Filter {
     Self { alpha, ..self }
   }
 
-  pub fn wrap(self, wrap: bool) -> Self {
-    Self { wrap, ..self }
+  pub fn audio_channel(self, audio_channel: AudioChannel) -> Self {
+    Self {
+      audio_channel,
+      ..self
+    }
+  }
+
+  pub fn blend(self, blend: Blend) -> Self {
+    Self { blend, ..self }
+  }
+
+  pub fn feather(self, feather: f32) -> Self {
+    Self { feather, ..self }
+  }
+
+  pub fn glow(self, glow: f32) -> Self {
+    Self { glow, ..self }
+  }
+
+  pub fn source(self, source: impl Into<String>) -> Self {
+    Self {
+      source: source.into(),
+      ..self
+    }
+  }
+
+  pub fn destination(self, destination: impl Into<String>) -> Self {
+    Self {
+      destination: destination.into(),
+      ..self
+    }
+  }
+
+  pub fn mask(self, mask: impl Into<String>) -> Self {
+    Self {
+      mask: Some(mask.into()),
+      ..self
+    }
+  }
+
+  pub fn sampling(self, sampling: Sampling) -> Self {
+    Self { sampling, ..self }
+  }
+
+  pub fn wrap(self, wrap: impl Into<Wrap>) -> Self {
+    Self {
+      wrap: wrap.into(),
+      ..self
+    }
   }
 
   pub fn times(self, times: u32) -> Self {
@@ -113,13 +553,22 @@ impl Default for Filter {
   fn default() -> Self {
     Self {
       alpha: 1.0,
+      audio_channel: AudioChannel::Mix,
+      blend: Blend::Normal,
+      color_space: ColorSpace::Rgb,
       color_transform: Similarity3::from_scaling(-1.0).into(),
       position_transform: Matrix3::identity(),
       coordinates: false,
       default_color: [0.0, 0.0, 0.0],
+      destination: "default".into(),
+      feather: 0.0,
       field: Field::All,
+      glow: 0.0,
+      mask: None,
+      source: "default".into(),
       times: 1,
-      wrap: false,
+      sampling: Sampling::Nearest,
+      wrap: Wrap::DefaultColor,
     }
   }
 }
@@ -128,42 +577,158 @@ impl Default for Filter {
 pub enum Field {
   All,
   Check,
-  Circle,
-  Cross,
+  Circle {
+    radius: f32,
+  },
+  Cross {
+    size: f32,
+    thickness: f32,
+  },
+  Custom(String),
   Equalizer,
-  Frequency,
-  Mod { divisor: u32, remainder: u32 },
-  Rows { on: u32, off: u32 },
-  Square,
+  Frequency {
+    threshold: f32,
+  },
+  Intersect(Box<Field>, Box<Field>),
+  Mod {
+    divisor: u32,
+    remainder: u32,
+  },
+  Rows {
+    on: u32,
+    off: u32,
+  },
+  SmoothUnion {
+    a: Box<Field>,
+    b: Box<Field>,
+    k: f32,
+  },
+  Spectrogram {
+    threshold: f32,
+  },
+  Square {
+    width: f32,
+    height: f32,
+  },
+  Subtract(Box<Field>, Box<Field>),
   TimeDomain,
   Top,
-  Wave,
-  X,
+  Transform {
+    field: Box<Field>,
+    transform: Matrix3,
+  },
+  Union(Box<Field>, Box<Field>),
+  Wave {
+    thickness: f32,
+  },
+  X {
+    size: f32,
+    radius: f32,
+  },
+}
+
+impl Field {
+  // Fields that took no parameters before they were parameterized are also
+  // accepted as bare names, with the parameters they used to have, so that
+  // filters saved before then can still be loaded
+  fn deserialize_legacy<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+
+    Ok(match value.as_str() {
+      Some("Circle") => Self::Circle { radius: 1.0 },
+      Some("Cross") => Self::Cross {
+        size: 1.0,
+        thickness: 0.25,
+      },
+      Some("Frequency") => Self::Frequency { threshold: 0.125 },
+      Some("Square") => Self::Square {
+        width: 1.0,
+        height: 1.0,
+      },
+      Some("Wave") => Self::Wave { thickness: 0.1 },
+      Some("X") => Self::X {
+        size: 2.0,
+        radius: 0.25,
+      },
+      _ => return Self::deserialize(value).map_err(de::Error::custom),
+    })
+  }
+
+  pub fn intersect(self, other: Field) -> Self {
+    Self::Intersect(Box::new(self), Box::new(other))
+  }
+
+  pub fn smooth_union(self, other: Field, k: f32) -> Self {
+    Self::SmoothUnion {
+      a: Box::new(self),
+      b: Box::new(other),
+      k,
+    }
+  }
+
+  pub fn subtract(self, other: Field) -> Self {
+    Self::Subtract(Box::new(self), Box::new(other))
+  }
+
+  pub fn transform(self, transform: impl Into<Matrix3>) -> Self {
+    Self::Transform {
+      field: Box::new(self),
+      transform: transform.into(),
+    }
+  }
+
+  pub fn union(self, other: Field) -> Self {
+    Self::Union(Box::new(self), Box::new(other))
+  }
 }
 
 #[derive(Default, Copy, Clone)]
 pub struct Frame {
   pub delta: f32,
   pub number: u64,
+  pub tempo: Tempo,
   pub time: f32,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Tempo {
+  pub beat: bool,
+  pub bpm: f32,
+  pub phase: f32,
+}
+
 pub struct System {
+  audio: AudioSummary,
+  done: bool,
+  frame: Frame,
   scope: DedicatedWorkerGlobalScope,
   listener: Option<Closure<dyn FnMut(MessageEvent)>>,
+  widgets: BTreeMap<String, serde_json::Value>,
+  widgets_sent: BTreeSet<String>,
 }
 
 impl System {
   fn new() -> Self {
     Self {
+      audio: AudioSummary::default(),
+      done: false,
+      frame: Frame::default(),
       scope: js_sys::global().dyn_into().unwrap(),
       listener: None,
+      widgets: BTreeMap::new(),
+      widgets_sent: BTreeSet::new(),
     }
   }
 
   fn execute_inner(&mut self, mut process: Box<dyn Process + 'static>) {
     let mut frame = Frame::default();
 
+    self.audio = AudioSummary::default();
+    self.done = false;
+    self.frame = frame;
+    self.widgets_sent.clear();
+
     if let Some(listener) = &self.listener {
       self
         .scope
@@ -176,14 +741,29 @@ impl System {
     let closure = Closure::wrap(Box::new(move |e: MessageEvent| {
       let event = serde_json::from_str(&e.data().as_string().unwrap()).unwrap();
 
-      if let Event::Frame(time) = event {
-        frame.delta = time - frame.time;
-        frame.time = time;
-        if process.clear() {
-          SYSTEM.with(|system| system.borrow_mut().send(Message::Clear));
+      match event {
+        Event::Frame { audio, tempo, time } => {
+          if SYSTEM.with(|system| system.borrow().done) {
+            return;
+          }
+          frame.delta = time - frame.time;
+          frame.time = time;
+          frame.tempo = tempo;
+          SYSTEM.with(|system| {
+            let mut system = system.borrow_mut();
+            system.audio = audio;
+            system.frame = frame;
+          });
+          if process.clear() {
+            SYSTEM.with(|system| system.borrow_mut().send(Message::Clear));
+          }
+          process.frame(frame);
+          frame.number += 1;
+        }
+        Event::Script(_) => {}
+        Event::Widget { key, value } => {
+          SYSTEM.with(|system| system.borrow_mut().widgets.insert(key, value));
         }
-        process.frame(frame);
-        frame.number += 1;
       }
     }) as Box<dyn FnMut(MessageEvent)>);
 
@@ -195,6 +775,21 @@ impl System {
     self.listener = Some(closure);
   }
 
+  // Create the widget the first time that its key is seen, so that programs
+  // which read widgets every frame don't flood the renderer with messages
+  fn widget(&mut self, name: &str, widget: Widget) -> Option<serde_json::Value> {
+    let key = widget.key(name);
+
+    if self.widgets_sent.insert(key.clone()) {
+      self.send(Message::Widget {
+        name: name.into(),
+        widget,
+      });
+    }
+
+    self.widgets.get(&key).cloned()
+  }
+
   fn send(&self, message: Message) {
     self
       .scope
@@ -205,7 +800,7 @@ impl System {
   }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum Widget {
   Checkbox,
@@ -218,6 +813,7 @@ pub enum Widget {
   Radio {
     options: Vec<String>,
   },
+  TapTempo,
 }
 
 impl Widget {
@@ -234,24 +830,71 @@ impl Widget {
       Self::Checkbox => "checkbox",
       Self::Slider { .. } => "slider",
       Self::Radio { .. } => "radio",
+      Self::TapTempo => "tap-tempo",
     }
   }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum Message {
+  #[serde(rename_all = "camelCase")]
+  Analyser {
+    fft_size: u32,
+    smoothing: f32,
+  },
+  Aspect(bool),
+  Audio(String),
+  AudioBins(bool),
+  Camera {
+    buffer: String,
+    placement: Placement,
+  },
+  Capture {
+    frames: u32,
+    fps: u32,
+    format: CaptureFormat,
+  },
   Clear,
-  DecibelRange { min: f32, max: f32 },
+  DecibelRange {
+    min: f32,
+    max: f32,
+  },
   Done,
   Error(String),
+  LoadImage {
+    image: String,
+    placement: Placement,
+    buffer: String,
+  },
+  OfflineAudio(Option<f64>),
   OscillatorFrequency(f32),
   OscillatorGain(f32),
+  Pause,
+  Play,
+  Precision(Precision),
+  Present(String),
   Record,
+  RecordSession,
+  RecordTiled,
   Render(Filter),
-  Resolution(u32),
+  Resolution {
+    width: u32,
+    height: u32,
+  },
   Save,
-  Widget { name: String, widget: Widget },
+  SaveSession,
+  #[serde(rename_all = "camelCase")]
+  SaveTiled {
+    width: u32,
+    height: u32,
+    tile_size: Option<u32>,
+  },
+  Seek(f64),
+  Widget {
+    name: String,
+    widget: Widget,
+  },
 }
 
 pub trait Process {
@@ -277,3 +920,91 @@ where
     self(frame);
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn legacy_fields() {
+    let filters = serde_json::from_str::<Vec<Filter>>(
+      r#"[
+        {"field": "All"},
+        {"field": "Circle"},
+        {"field": "Cross"},
+        {"field": "Frequency"},
+        {"field": "Square"},
+        {"field": "Wave"},
+        {"field": "X"},
+        {"field": {"X": {"size": 1.0, "radius": 0.5}}}
+      ]"#,
+    )
+    .unwrap();
+
+    assert!(matches!(filters[0].field, Field::All));
+    assert!(matches!(filters[1].field, Field::Circle { radius } if radius == 1.0));
+    assert!(matches!(
+      filters[2].field,
+      Field::Cross { size, thickness } if size == 1.0 && thickness == 0.25
+    ));
+    assert!(matches!(
+      filters[3].field,
+      Field::Frequency { threshold } if threshold == 0.125
+    ));
+    assert!(matches!(
+      filters[4].field,
+      Field::Square { width, height } if width == 1.0 && height == 1.0
+    ));
+    assert!(matches!(filters[5].field, Field::Wave { thickness } if thickness == 0.1));
+    assert!(matches!(
+      filters[6].field,
+      Field::X { size, radius } if size == 2.0 && radius == 0.25
+    ));
+    assert!(matches!(
+      filters[7].field,
+      Field::X { size, radius } if size == 1.0 && radius == 0.5
+    ));
+  }
+
+  #[test]
+  fn legacy_wrap() {
+    let filters = serde_json::from_str::<Vec<Filter>>(
+      r#"[
+        {"wrap": false},
+        {"wrap": true},
+        {"wrap": "mirror"}
+      ]"#,
+    )
+    .unwrap();
+
+    assert_eq!(filters[0].wrap, Wrap::DefaultColor);
+    assert_eq!(filters[1].wrap, Wrap::Repeat);
+    assert_eq!(filters[2].wrap, Wrap::Mirror);
+  }
+
+  #[test]
+  fn audio_summary_bands() {
+    // Four bins of 1000 Hz each at a sample rate of 8000 Hz, with linear
+    // magnitudes of 1.0, 0.1, 0.1, and 0.1
+    let summary = AudioSummary::new(
+      &[0.5, -1.0],
+      &[0.0, -20.0, -20.0, -20.0],
+      &[1.0, 0.5, 0.5, 0.5],
+      8000.0,
+      false,
+    );
+
+    assert_eq!(summary.bass, 1.0);
+    assert!((summary.mid - 0.03).abs() < 1e-6);
+    assert_eq!(summary.treble, 0.0);
+    assert_eq!(summary.peak, 1.0);
+    assert_eq!(summary.rms, 0.625f32.sqrt());
+    assert!((summary.centroid - 600.0 / 1.3).abs() < 1e-3);
+    assert_eq!(summary.frequency, None);
+
+    let summary = AudioSummary::new(&[], &[0.0, 0.0], &[1.0, 1.0], 8000.0, true);
+
+    assert_eq!(summary.frequency, Some(vec![1.0, 1.0]));
+    assert_eq!(summary.time_domain, Some(Vec::new()));
+  }
+}
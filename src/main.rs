@@ -1,47 +1,94 @@
 use {
   crate::{
-    add_event_listener::AddEventListener, app::App, cast::Cast, error::Error,
-    get_document::GetDocument, gpu::Gpu, select::Select, stderr::Stderr, window::window,
+    add_event_listener::AddEventListener,
+    analyser::Analyser,
+    app::App,
+    audio::Audio,
+    beat_detector::BeatDetector,
+    blit::blit,
+    buffer::Buffer,
+    camera::Camera,
+    capture::Capture,
+    cast::Cast,
+    error::Error,
+    field_program::FieldProgram,
+    get_document::GetDocument,
+    gpu::Gpu,
+    metadata::Metadata,
+    program::Program,
+    program_cache::ProgramCache,
+    select::Select,
+    session::{Replay, Session},
+    source_image::SourceImage,
+    stderr::Stderr,
+    tile::Tile,
+    window::window,
+  },
+  degenerate::{
+    AudioChannel, AudioSummary, CaptureFormat, Event, Field, Filter, Message, Placement, Precision,
+    Sampling, Tempo, Vector2, Widget, Wrap,
   },
-  degenerate::{Event, Field, Filter, Message, Widget},
   hex::FromHexError,
-  image::{ImageBuffer, ImageError, ImageOutputFormat},
+  image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, DynamicImage, GenericImage, GenericImageView, ImageBuffer, ImageError,
+    ImageOutputFormat, RgbaImage,
+  },
   js_sys::{Float32Array, Promise},
   lazy_static::lazy_static,
+  serde::{Deserialize, Serialize},
   std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
     convert::Infallible,
-    f32,
+    f32::{self, consts::TAU},
     fmt::{self, Display, Formatter},
-    io::Cursor,
+    io::{self, Cursor, Write},
     mem,
     num::TryFromIntError,
     ops::Deref,
+    rc::Rc,
     str::{self, Utf8Error},
     string::ToString,
     sync::{Arc, Mutex},
   },
   wasm_bindgen::{closure::Closure, convert::FromWasmAbi, JsCast, JsValue},
   web_sys::{
-    AnalyserNode, AudioContext, Document, EventTarget, GainNode, HtmlAnchorElement,
+    AnalyserNode, AudioBuffer, AudioBufferSourceNode, AudioContext, AudioNode, ChannelCountMode,
+    ChannelInterpretation, Document, DragEvent, EventTarget, File, GainNode, HtmlAnchorElement,
     HtmlButtonElement, HtmlCanvasElement, HtmlDivElement, HtmlElement, HtmlInputElement,
-    HtmlLabelElement, HtmlOptionElement, HtmlSelectElement, HtmlSpanElement, HtmlTextAreaElement,
-    KeyboardEvent, MediaStream, MediaStreamConstraints, MessageEvent, OscillatorNode,
-    WebGl2RenderingContext, WebGlContextAttributes, WebGlFramebuffer, WebGlTexture,
-    WebGlUniformLocation, Window, Worker, WorkerOptions, WorkerType,
+    HtmlLabelElement, HtmlMediaElement, HtmlOptionElement, HtmlSelectElement, HtmlSpanElement,
+    HtmlTextAreaElement, HtmlVideoElement, KeyboardEvent, MediaStream, MediaStreamConstraints,
+    MediaStreamTrack, MessageEvent, OscillatorNode, WebGl2RenderingContext, WebGlContextAttributes,
+    WebGlFramebuffer, WebGlProgram, WebGlShader, WebGlTexture, WebGlUniformLocation, Window,
+    Worker, WorkerOptions, WorkerType,
   },
+  zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipWriter},
 };
 
 type Result<T = (), E = Error> = std::result::Result<T, E>;
 
 mod add_event_listener;
+mod analyser;
 mod app;
+mod audio;
+mod beat_detector;
+mod blit;
+mod buffer;
+mod camera;
+mod capture;
 mod cast;
 mod error;
+mod field_program;
 mod get_document;
 mod gpu;
+mod metadata;
+mod program;
+mod program_cache;
 mod select;
+mod session;
+mod source_image;
 mod stderr;
+mod tile;
 mod window;
 
 fn main() {
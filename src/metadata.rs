@@ -0,0 +1,84 @@
+use super::*;
+
+const PATH: &str = "Path";
+const RESOLUTION: &str = "Resolution";
+const SCRIPT: &str = "Script";
+const SOFTWARE: &str = "Software";
+const TIMESTAMP: &str = "Creation Time";
+
+// A record of how an image was made, embedded in saved PNGs as text chunks so
+// that the script can be loaded back from the image
+pub(crate) struct Metadata {
+  pub(crate) script: String,
+  pub(crate) timestamp: String,
+}
+
+impl Metadata {
+  // Encode `image` as a PNG with tEXt chunks for the share path, resolution,
+  // and timestamp, and an iTXt chunk for the script, since it may contain
+  // characters that aren't Latin-1
+  pub(crate) fn encode(&self, image: &DynamicImage) -> Result<Vec<u8>> {
+    let (width, height) = image.dimensions();
+
+    let mut output = Vec::new();
+
+    let mut encoder = png::Encoder::new(&mut output, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+
+    // PNG stores 16 bit samples in big-endian order
+    let data = match image {
+      DynamicImage::ImageRgba16(image) => {
+        encoder.set_depth(png::BitDepth::Sixteen);
+        image
+          .as_raw()
+          .iter()
+          .flat_map(|sample| sample.to_be_bytes())
+          .collect()
+      }
+      _ => {
+        encoder.set_depth(png::BitDepth::Eight);
+        image.to_rgba8().into_raw()
+      }
+    };
+
+    encoder.add_text_chunk(SOFTWARE.into(), "degenerate".into())?;
+    encoder.add_text_chunk(TIMESTAMP.into(), self.timestamp.clone())?;
+    encoder.add_text_chunk(RESOLUTION.into(), format!("{width}x{height}"))?;
+
+    if !self.script.is_empty() {
+      encoder.add_text_chunk(
+        PATH.into(),
+        format!("/program/{}", hex::encode(&self.script)),
+      )?;
+      encoder.add_itxt_chunk(SCRIPT.into(), self.script.clone())?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(output)
+  }
+
+  // Extract the script from a PNG saved by `encode`
+  pub(crate) fn script(png: &[u8]) -> Result<String> {
+    let reader = png::Decoder::new(png).read_info()?;
+
+    let info = reader.info();
+
+    if let Some(chunk) = info.utf8_text.iter().find(|chunk| chunk.keyword == SCRIPT) {
+      return Ok(chunk.get_text()?);
+    }
+
+    if let Some(hex) = info
+      .uncompressed_latin1_text
+      .iter()
+      .find(|chunk| chunk.keyword == PATH)
+      .and_then(|chunk| chunk.text.strip_prefix("/program/"))
+    {
+      return Ok(str::from_utf8(&hex::decode(hex)?)?.into());
+    }
+
+    Err("Image does not contain a script".into())
+  }
+}
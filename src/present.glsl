@@ -0,0 +1,14 @@
+#version 300 es
+
+precision highp float;
+
+uniform ivec2 offset;
+uniform sampler2D source;
+
+out vec4 output_color;
+
+// Copy texels from `source` to the canvas, for buffers which can't be copied
+// with `blitFramebuffer`
+void main() {
+  output_color = texelFetch(source, ivec2(gl_FragCoord.xy) + offset, 0);
+}
@@ -0,0 +1,192 @@
+use super::*;
+
+// A linked shader program, along with the locations of its active uniforms.
+// One filter program is compiled for each distinct set of custom field
+// sources, and cached in `ProgramCache`.
+pub(crate) struct Program {
+  program: WebGlProgram,
+  uniforms: BTreeMap<String, WebGlUniformLocation>,
+}
+
+impl Program {
+  pub(crate) fn filter(
+    gl: &WebGl2RenderingContext,
+    vertex: &WebGlShader,
+    sources: &[String],
+  ) -> Result<Self> {
+    Self::new(gl, vertex, &Self::fragment_source(sources), sources)
+  }
+
+  pub(crate) fn present(gl: &WebGl2RenderingContext, vertex: &WebGlShader) -> Result<Self> {
+    Self::new(gl, vertex, include_str!("present.glsl"), &[])
+  }
+
+  fn new(
+    gl: &WebGl2RenderingContext,
+    vertex: &WebGlShader,
+    fragment_source: &str,
+    sources: &[String],
+  ) -> Result<Self> {
+    let program = gl.create_program().ok_or("Failed to create program")?;
+
+    let fragment = gl
+      .create_shader(WebGl2RenderingContext::FRAGMENT_SHADER)
+      .ok_or("Failed to create shader")?;
+
+    gl.shader_source(&fragment, fragment_source);
+    gl.compile_shader(&fragment);
+
+    if !gl.get_shader_parameter(&fragment, WebGl2RenderingContext::COMPILE_STATUS) {
+      let log = gl
+        .get_shader_info_log(&fragment)
+        .ok_or("Failed to get shader info log")?;
+      gl.delete_shader(Some(&fragment));
+      gl.delete_program(Some(&program));
+      return Err(Self::compile_error(&log, sources).into());
+    }
+
+    gl.attach_shader(&program, vertex);
+    gl.attach_shader(&program, &fragment);
+
+    gl.link_program(&program);
+
+    gl.delete_shader(Some(&fragment));
+
+    if !gl.get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS) {
+      let log = gl
+        .get_program_info_log(&program)
+        .ok_or("Failed to get program log info")?;
+      gl.delete_program(Some(&program));
+      return Err(log.into());
+    }
+
+    gl.use_program(Some(&program));
+
+    let uniform_count = gl
+      .get_program_parameter(&program, WebGl2RenderingContext::ACTIVE_UNIFORMS)
+      .cast::<js_sys::Number>()?
+      .value_of() as u32;
+
+    let uniforms = (0..uniform_count)
+      .map(|i| {
+        let info = gl.get_active_uniform(&program, i).unwrap();
+        let name = info.name();
+        let location = gl.get_uniform_location(&program, &name).unwrap();
+        (name, location)
+      })
+      .collect::<BTreeMap<String, WebGlUniformLocation>>();
+
+    for (i, sampler) in [
+      "source",
+      "audio_time_domain",
+      "audio_frequency",
+      "original",
+      "mask",
+      "audio_spectrogram",
+    ]
+    .iter()
+    .enumerate()
+    {
+      if let Some(location) = uniforms.get(*sampler) {
+        gl.uniform1i(Some(location), i.try_into()?);
+      }
+    }
+
+    Ok(Self { program, uniforms })
+  }
+
+  pub(crate) fn vertex(gl: &WebGl2RenderingContext) -> Result<WebGlShader> {
+    let vertex = gl
+      .create_shader(WebGl2RenderingContext::VERTEX_SHADER)
+      .ok_or("Failed to create shader")?;
+
+    gl.shader_source(&vertex, include_str!("vertex.glsl"));
+    gl.compile_shader(&vertex);
+
+    if !gl.get_shader_parameter(&vertex, WebGl2RenderingContext::COMPILE_STATUS) {
+      return Err(
+        gl.get_shader_info_log(&vertex)
+          .ok_or("Failed to get shader info log")?
+          .into(),
+      );
+    }
+
+    Ok(vertex)
+  }
+
+  pub(crate) fn delete(&self, gl: &WebGl2RenderingContext) {
+    gl.delete_program(Some(&self.program));
+  }
+
+  pub(crate) fn program(&self) -> &WebGlProgram {
+    &self.program
+  }
+
+  pub(crate) fn uniform(&self, name: &str) -> &WebGlUniformLocation {
+    self
+      .uniforms
+      .get(name)
+      .ok_or_else(|| format!("Uniform `{name}` is missing.",))
+      .unwrap()
+  }
+
+  // Append each custom field source to `fragment.glsl`, followed by the
+  // `field_custom` function that dispatches to them. Sources must define `f`,
+  // which is renamed so that multiple sources can coexist. Each source is
+  // given its own source string number with `#line`, so compiler errors refer
+  // to lines within that source.
+  fn fragment_source(sources: &[String]) -> String {
+    let mut fragment = include_str!("fragment.glsl").to_owned();
+
+    for (i, source) in sources.iter().enumerate() {
+      fragment.push_str(&format!(
+        "#define f field_custom_{i}_f\n#line 1 {}\n{source}\nfloat field_custom_{i}(vec2 p) {{\n  return f(p);\n}}\n#undef f\n",
+        i + 1
+      ));
+    }
+
+    fragment.push_str("float field_custom(uint index, vec2 p) {\n  switch (index) {\n");
+
+    for i in 0..sources.len() {
+      fragment.push_str(&format!(
+        "    case {i}u:\n      return field_custom_{i}(p);\n"
+      ));
+    }
+
+    fragment.push_str("    default:\n      return field_none();\n  }\n}\n");
+
+    fragment
+  }
+
+  // Rewrite compiler errors in custom field sources, which look like
+  // `ERROR: 1:3: 'x' : undeclared identifier`, to refer to the custom field.
+  fn compile_error(log: &str, sources: &[String]) -> String {
+    log
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .map(|line| {
+        let location = line.strip_prefix("ERROR: ").and_then(|rest| {
+          let (string, rest) = rest.split_once(':')?;
+          let (line, message) = rest.split_once(':')?;
+          Some((
+            string.parse::<usize>().ok()?,
+            line.parse::<usize>().ok()?,
+            message,
+          ))
+        });
+
+        match location {
+          Some((string, line, message)) if string > 0 && string <= sources.len() => {
+            if line > sources[string - 1].lines().count() {
+              format!("Custom field error:{message} (custom fields must define `float f(vec2 p)`)")
+            } else {
+              format!("Custom field error on line {line}:{message}")
+            }
+          }
+          _ => line.to_owned(),
+        }
+      })
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+}
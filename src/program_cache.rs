@@ -0,0 +1,77 @@
+use super::*;
+
+// Filter programs, one for each distinct set of custom field sources, ordered
+// from least to most recently used, so the program in use is always last. At
+// most `CAPACITY` programs are kept, and evicted programs are deleted, so that
+// scripts which generate new sources every frame don't exhaust the context.
+// Sources that fail to compile are remembered with their error, so that they
+// aren't recompiled on every render.
+pub(crate) struct ProgramCache {
+  failures: VecDeque<(Vec<String>, String)>,
+  programs: VecDeque<(Vec<String>, Program)>,
+}
+
+impl ProgramCache {
+  const CAPACITY: usize = 16;
+
+  pub(crate) fn new(gl: &WebGl2RenderingContext, vertex: &WebGlShader) -> Result<Self> {
+    Ok(Self {
+      failures: VecDeque::new(),
+      programs: VecDeque::from([(Vec::new(), Program::filter(gl, vertex, &[])?)]),
+    })
+  }
+
+  pub(crate) fn current(&self) -> &Program {
+    &self.programs.back().unwrap().1
+  }
+
+  // Make the program for `sources` current, compiling it if necessary
+  pub(crate) fn use_program(
+    &mut self,
+    gl: &WebGl2RenderingContext,
+    vertex: &WebGlShader,
+    sources: &[String],
+  ) -> Result {
+    if self.programs.back().unwrap().0 == sources {
+      return Ok(());
+    }
+
+    if let Some((_, error)) = self.failures.iter().find(|(failed, _)| failed == sources) {
+      return Err(error.clone().into());
+    }
+
+    let entry = match self
+      .programs
+      .iter()
+      .position(|(cached, _)| cached == sources)
+    {
+      Some(i) => self.programs.remove(i).unwrap(),
+      None => match Program::filter(gl, vertex, sources) {
+        Ok(program) => (sources.to_vec(), program),
+        Err(error) => {
+          let error = error.to_string();
+
+          if self.failures.len() == Self::CAPACITY {
+            self.failures.pop_front();
+          }
+
+          self.failures.push_back((sources.to_vec(), error.clone()));
+
+          return Err(error.into());
+        }
+      },
+    };
+
+    gl.use_program(Some(entry.1.program()));
+
+    self.programs.push_back(entry);
+
+    if self.programs.len() > Self::CAPACITY {
+      if let Some((_, program)) = self.programs.pop_front() {
+        program.delete(gl);
+      }
+    }
+
+    Ok(())
+  }
+}
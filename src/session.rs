@@ -0,0 +1,62 @@
+use super::*;
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Entry {
+  frame: u64,
+  message: Message,
+}
+
+#[derive(Deserialize, Serialize)]
+pub(crate) struct Session {
+  #[serde(skip)]
+  start: u64,
+  entries: Vec<Entry>,
+}
+
+impl Session {
+  pub(crate) fn new(start: u64) -> Self {
+    Self {
+      start,
+      entries: Vec::new(),
+    }
+  }
+
+  pub(crate) fn record(&mut self, frame: u64, message: Message) {
+    self.entries.push(Entry {
+      frame: frame - self.start,
+      message,
+    });
+  }
+
+  pub(crate) fn replay(self, start: u64) -> Replay {
+    Replay {
+      start,
+      entries: self.entries.into(),
+    }
+  }
+}
+
+pub(crate) struct Replay {
+  start: u64,
+  entries: VecDeque<Entry>,
+}
+
+impl Replay {
+  pub(crate) fn is_done(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub(crate) fn messages(&mut self, frame: u64) -> Vec<Message> {
+    let mut messages = Vec::new();
+
+    while let Some(entry) = self.entries.front() {
+      if entry.frame > frame - self.start {
+        break;
+      }
+
+      messages.push(self.entries.pop_front().unwrap().message);
+    }
+
+    messages
+  }
+}
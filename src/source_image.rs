@@ -0,0 +1,75 @@
+use super::*;
+
+// An image loaded into a buffer. Buffers are initialized with their image
+// whenever they are created, so loaded images survive resizes and precision
+// changes, and appear in tiled exports.
+pub(crate) struct SourceImage {
+  image: RgbaImage,
+  placement: Placement,
+}
+
+impl SourceImage {
+  pub(crate) fn new(image: RgbaImage, placement: Placement) -> Self {
+    Self { image, placement }
+  }
+
+  // Draw the image into `texture`, which holds the region of an image of size
+  // `size` whose bottom left corner is at `offset`
+  pub(crate) fn draw(
+    &self,
+    gl: &WebGl2RenderingContext,
+    texture: &WebGlTexture,
+    precision: Precision,
+    size: (u32, u32),
+    offset: (i32, i32),
+  ) -> Result {
+    let (width, height) = self.image.dimensions();
+
+    let source = Buffer::create_texture(gl, width, height, precision)?;
+
+    let data: js_sys::Object = if precision == Precision::Rgba8 {
+      js_sys::Uint8Array::from(self.image.as_raw().as_slice()).into()
+    } else {
+      Float32Array::from(
+        self
+          .image
+          .as_raw()
+          .iter()
+          .map(|&n| n as f32 / 255.0)
+          .collect::<Vec<f32>>()
+          .as_slice(),
+      )
+      .into()
+    };
+
+    gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+      WebGl2RenderingContext::TEXTURE_2D,
+      0,
+      0,
+      0,
+      width as i32,
+      height as i32,
+      WebGl2RenderingContext::RGBA,
+      if precision == Precision::Rgba8 {
+        WebGl2RenderingContext::UNSIGNED_BYTE
+      } else {
+        WebGl2RenderingContext::FLOAT
+      },
+      Some(&data),
+    )?;
+
+    let result = blit(
+      gl,
+      &source,
+      (width, height),
+      self.placement,
+      texture,
+      size,
+      offset,
+    );
+
+    gl.delete_texture(Some(&source));
+
+    result
+  }
+}
@@ -0,0 +1,190 @@
+use super::*;
+
+// A region of a larger image, rendered into its own set of buffers. Buffers
+// extend `padding` pixels past the region on each side, so that filters which
+// sample neighboring pixels see the correct colors near the region's edges.
+#[derive(Clone, Copy)]
+pub(crate) struct Tile {
+  pub(crate) height: u32,
+  pub(crate) image_height: u32,
+  pub(crate) image_width: u32,
+  pub(crate) padding: u32,
+  pub(crate) width: u32,
+  pub(crate) x: u32,
+  pub(crate) y: u32,
+}
+
+impl Tile {
+  // Split an image into tiles whose buffers are at most `size` pixels on a side
+  pub(crate) fn split(
+    image_width: u32,
+    image_height: u32,
+    size: u32,
+    padding: u32,
+  ) -> Result<Vec<Self>> {
+    if padding * 2 >= size {
+      return Err(
+        format!(
+          "Filters move pixels up to {padding} pixels, which tiles {size} pixels on a side cannot cover"
+        )
+        .into(),
+      );
+    }
+
+    let step = size - padding * 2;
+
+    let mut tiles = Vec::new();
+
+    for y in (0..image_height).step_by(step as usize) {
+      for x in (0..image_width).step_by(step as usize) {
+        tiles.push(Self {
+          height: step.min(image_height - y),
+          image_height,
+          image_width,
+          padding,
+          width: step.min(image_width - x),
+          x,
+          y,
+        });
+      }
+    }
+
+    Ok(tiles)
+  }
+
+  // Padding needed so that replaying `filters` into tiles of a `width` by
+  // `height` image gives the same colors as rendering the whole image. Each
+  // render reads pixels as far away as its position transform moves the image's
+  // corners, plus the reach of its sampling mode, and renders accumulate.
+  // Samples that wrap around the image's edge may come from anywhere, so
+  // filters that wrap, repeat, mirror, or clamp samples that leave the image
+  // are rejected.
+  pub(crate) fn padding<'a>(
+    filters: impl IntoIterator<Item = &'a Filter>,
+    width: u32,
+    height: u32,
+  ) -> Result<u32> {
+    let resolution = width.max(height) as f32;
+
+    let extent = Vector2::new(width as f32 / resolution, height as f32 / resolution);
+
+    // Centers of the corner pixels
+    let corner = extent.add_scalar(-1.0 / resolution);
+
+    let mut padding = 0.0;
+
+    for filter in filters {
+      if filter.coordinates {
+        continue;
+      }
+
+      // Bilinear and bicubic sampling interpolate from pixels on the far side
+      // of the image once samples pass the centers of its edge pixels
+      let (reach, margin) = match filter.sampling {
+        Sampling::Nearest => (1.0, 0.0),
+        Sampling::Bilinear => (1.0, 1.0 / resolution),
+        Sampling::Bicubic => (2.0, 1.0 / resolution),
+      };
+
+      let mut distance = 0.0f32;
+
+      for (x, y) in [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)] {
+        let position = Vector2::new(x * corner.x, y * corner.y);
+
+        let sample = (filter.position_transform * position.push(1.0)).xy();
+
+        if filter.wrap != Wrap::DefaultColor
+          && (sample.x.abs() > extent.x - margin || sample.y.abs() > extent.y - margin)
+        {
+          return Err(
+            "Filters that wrap pixels across the edge of the image cannot be saved with tiles"
+              .into(),
+          );
+        }
+
+        distance = distance.max((sample - position).norm());
+      }
+
+      if distance > 0.0 || filter.sampling != Sampling::Nearest {
+        padding += (distance * resolution / 2.0 + reach) * filter.times as f32;
+      }
+    }
+
+    Ok(padding.ceil() as u32)
+  }
+
+  pub(crate) fn buffer_size(&self) -> (u32, u32) {
+    (
+      self.width + self.padding * 2,
+      self.height + self.padding * 2,
+    )
+  }
+
+  // Position of the bottom left corner of the tile's buffers within the image
+  pub(crate) fn offset(&self) -> (i32, i32) {
+    (
+      self.x as i32 - self.padding as i32,
+      self.y as i32 - self.padding as i32,
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use {super::*, degenerate::Matrix3};
+
+  #[test]
+  fn split() {
+    let tiles = Tile::split(100, 50, 40, 5).unwrap();
+
+    assert_eq!(
+      tiles
+        .iter()
+        .map(|tile| (tile.x, tile.y, tile.width, tile.height))
+        .collect::<Vec<(u32, u32, u32, u32)>>(),
+      [
+        (0, 0, 30, 30),
+        (30, 0, 30, 30),
+        (60, 0, 30, 30),
+        (90, 0, 10, 30),
+        (0, 30, 30, 20),
+        (30, 30, 30, 20),
+        (60, 30, 30, 20),
+        (90, 30, 10, 20),
+      ],
+    );
+
+    for tile in &tiles {
+      assert_eq!(tile.image_width, 100);
+      assert_eq!(tile.image_height, 50);
+      assert_eq!(tile.buffer_size(), (tile.width + 10, tile.height + 10));
+      assert_eq!(tile.offset(), (tile.x as i32 - 5, tile.y as i32 - 5));
+    }
+
+    assert!(Tile::split(100, 50, 10, 5).is_err());
+  }
+
+  #[test]
+  fn padding() {
+    let mut filter = Filter::new();
+
+    assert_eq!(Tile::padding([&filter], 256, 256).unwrap(), 0);
+
+    filter.times = 3;
+    filter.sampling = Sampling::Bicubic;
+    assert_eq!(Tile::padding([&filter], 256, 256).unwrap(), 6);
+
+    filter.times = 1;
+    filter.sampling = Sampling::Nearest;
+    filter.position_transform = Matrix3::new_translation(&Vector2::new(0.5, 0.0));
+    assert_eq!(Tile::padding([&filter], 256, 256).unwrap(), 65);
+    assert_eq!(Tile::padding([&filter], 1024, 256).unwrap(), 257);
+    assert_eq!(Tile::padding([&filter, &filter], 256, 256).unwrap(), 130);
+
+    filter.wrap = Wrap::Repeat;
+    assert!(Tile::padding([&filter], 256, 256).is_err());
+
+    filter.position_transform = Matrix3::new_scaling(0.5);
+    assert_eq!(Tile::padding([&filter], 256, 256).unwrap(), 92);
+  }
+}
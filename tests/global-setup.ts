@@ -15,6 +15,8 @@ export default async function () {
 
   await execFile('cargo', ['build', '--target', 'wasm32-unknown-unknown']);
 
+  await execFile('cargo', ['build', '--release', '--package', 'render']);
+
   await execFile(
     'wasm-bindgen',
     [
@@ -21,6 +21,39 @@ async function imageBuffer(page) {
   );
 }
 
+function wav(seconds, ...channels) {
+  const sampleRate = 44100;
+  const samples = sampleRate * seconds;
+  const blockAlign = channels.length * 2;
+  const data = Buffer.alloc(44 + samples * blockAlign);
+  data.write('RIFF', 0);
+  data.writeUInt32LE(36 + samples * blockAlign, 4);
+  data.write('WAVEfmt ', 8);
+  data.writeUInt32LE(16, 16);
+  data.writeUInt16LE(1, 20);
+  data.writeUInt16LE(channels.length, 22);
+  data.writeUInt32LE(sampleRate, 24);
+  data.writeUInt32LE(sampleRate * blockAlign, 28);
+  data.writeUInt16LE(blockAlign, 32);
+  data.writeUInt16LE(16, 34);
+  data.write('data', 36);
+  data.writeUInt32LE(samples * blockAlign, 40);
+  for (let i = 0; i < samples; i++) {
+    channels.forEach((signal, channel) => {
+      const sample = signal(i / sampleRate);
+      data.writeInt16LE(
+        Math.round(sample * 32767),
+        44 + i * blockAlign + channel * 2
+      );
+    });
+  }
+  return `data:audio/wav;base64,${data.toString('base64')}`;
+}
+
+function sineWav(frequency) {
+  return wav(1, (time) => Math.sin(2 * Math.PI * frequency * time));
+}
+
 async function run(page, script) {
   await page.locator('textarea').fill(script);
   await page.keyboard.down('Shift');
@@ -111,6 +144,60 @@ for (const test in tests) {
   imageTest(test, tests[test]);
 }
 
+// Features that the CPU renderer does not reproduce: `clear` and `reboot` send
+// messages other than `render`, `custom_field` uses a GLSL field, and
+// `default_program` runs a Rust program.
+const cpuExclusions = new Set([
+  'clear',
+  'custom_field',
+  'default_program',
+  'reboot',
+]);
+
+for (const name in tests) {
+  if (cpuExclusions.has(name)) {
+    continue;
+  }
+
+  test(`cpu-${name}`, async ({ page }) => {
+    test.setTimeout(30 * 1000);
+
+    const [download] = await Promise.all([
+      page.waitForEvent('download'),
+      run(page, `recordSession();\n${tests[name]}\nsaveSession();`),
+    ]);
+
+    const entries = JSON.parse(
+      (await fs.promises.readFile(await download.path())).toString()
+    ).entries;
+
+    const filters = entries.map(({ message }) => {
+      expect(Object.keys(message)).toEqual(['render']);
+      return message.render;
+    });
+
+    const input = test.info().outputPath('filters.json');
+    const output = test.info().outputPath('cpu.png');
+
+    await fs.promises.writeFile(input, JSON.stringify(filters));
+
+    await execFile('../target/release/render', [
+      '--resolution',
+      '256',
+      '--output',
+      output,
+      input,
+    ]);
+
+    await expect(
+      Buffer.compare(
+        png.decode(await fs.promises.readFile(output)).data,
+        png.decode(await fs.promises.readFile(`../images/${name}.png`)).data
+      )
+    ).toBe(0);
+  });
+}
+
 test('forbid-unused-images', async () => {
   let testNames = new Set(Object.getOwnPropertyNames(tests));
 
@@ -292,6 +379,628 @@ test('radio-initial', async ({ page }) => {
   );
 });
 
+test('mask-unbound', async ({ page }) => {
+  const script = `
+    destination('mask');
+    times(2);
+    x();
+    render();
+    present('mask');
+  `;
+
+  await run(page, `mask('mask'); render(); mask(null); ${script}`);
+
+  const masked = png.decode(await imageBuffer(page)).data;
+
+  await page.goto(`http://localhost:${process.env.PORT}`);
+  await page.evaluate('window.preserveDrawingBuffer = true');
+  await page.waitForSelector('html.ready');
+
+  await run(page, script);
+
+  await expect(
+    Buffer.compare(masked, png.decode(await imageBuffer(page)).data)
+  ).toBe(0);
+});
+
+test('session', async ({ page }) => {
+  const [download] = await Promise.all([
+    page.waitForEvent('download'),
+    run(page, 'recordSession(); x(); render(); saveSession();'),
+  ]);
+
+  await expect(download.suggestedFilename()).toBe('degenerate-session.json');
+
+  const session = await fs.promises.readFile(await download.path());
+
+  const entries = JSON.parse(session.toString()).entries;
+  await expect(entries.length).toBe(1);
+  await expect(entries[0].message.render.field.X).toBeTruthy();
+
+  await page.goto(`http://localhost:${process.env.PORT}`);
+  await page.evaluate('window.preserveDrawingBuffer = true');
+  await page.waitForSelector('html.ready');
+
+  await page.setInputFiles('input#session', {
+    name: 'degenerate-session.json',
+    mimeType: 'application/json',
+    buffer: session,
+  });
+
+  await page.waitForSelector('html.done');
+
+  await expect(
+    Buffer.compare(
+      png.decode(await imageBuffer(page)).data,
+      png.decode(await fs.promises.readFile('../images/x.png')).data
+    )
+  ).toBe(0);
+});
+
+test('session-stops-script', async ({ page }) => {
+  const [download] = await Promise.all([
+    page.waitForEvent('download'),
+    run(page, 'recordSession(); x(); render(); saveSession();'),
+  ]);
+
+  const session = await fs.promises.readFile(await download.path());
+
+  await page
+    .locator('textarea')
+    .fill('while (true) { await frame(); circle(); render(); }');
+  await page.keyboard.down('Shift');
+  await page.keyboard.press('Enter');
+
+  await page.setInputFiles('input#session', {
+    name: 'degenerate-session.json',
+    mimeType: 'application/json',
+    buffer: session,
+  });
+
+  await page.waitForSelector('html.done');
+
+  await sleep(200);
+
+  await expect(
+    Buffer.compare(
+      png.decode(await imageBuffer(page)).data,
+      png.decode(await fs.promises.readFile('../images/x.png')).data
+    )
+  ).toBe(0);
+});
+
+test('save-metadata', async ({ page }) => {
+  const script = 'x(); render(); save();';
+
+  const [download] = await Promise.all([
+    page.waitForEvent('download'),
+    run(page, script),
+  ]);
+
+  await expect(download.suggestedFilename()).toBe('degenerate.png');
+
+  const image = await fs.promises.readFile(await download.path());
+
+  await page.locator('textarea').fill('');
+
+  await page.setInputFiles('input#image', {
+    name: 'degenerate.png',
+    mimeType: 'image/png',
+    buffer: image,
+  });
+
+  await expect(page.locator('textarea')).toHaveValue(script);
+});
+
+test('load-image', async ({ page }) => {
+  const image = await fs.promises.readFile('../images/x.png');
+
+  await run(
+    page,
+    `await loadImage('data:image/png;base64,${image.toString('base64')}');`
+  );
+
+  await expect(
+    Buffer.compare(
+      png.decode(await imageBuffer(page)).data,
+      png.decode(image).data
+    )
+  ).toBe(0);
+});
+
+test('load-image-clear', async ({ page }) => {
+  const image = await fs.promises.readFile('../images/x.png');
+
+  // Clearing resets the buffer to the loaded image, rather than dropping it
+  await run(
+    page,
+    `
+      await loadImage('data:image/png;base64,${image.toString('base64')}');
+      for (let i = 0; i < 10; i++) {
+        clear();
+        await frame();
+      }
+      present('default');
+    `
+  );
+
+  await expect(
+    Buffer.compare(
+      png.decode(await imageBuffer(page)).data,
+      png.decode(image).data
+    )
+  ).toBe(0);
+});
+
+test('camera', async ({ page, browserName }) => {
+  test.skip(browserName !== 'chromium', 'fake media devices require chromium');
+
+  // The fake camera is 640 by 480, so fitting it into the 256 by 256 canvas
+  // leaves 32 empty rows above and below it. Clearing every frame, as most
+  // scripts do, leaves the camera running.
+  await run(
+    page,
+    `
+      camera('camera', 'fit');
+      present('camera');
+      for (let i = 0; i < 60; i++) {
+        clear();
+        await frame();
+      }
+      await frame();
+      await frame();
+    `
+  );
+
+  const rowLit = (pixels, row) =>
+    pixels
+      .subarray(row * 256 * 4, (row + 1) * 256 * 4)
+      .some((value, i) => i % 4 != 3 && value != 0);
+
+  let pixels = png.decode(await imageBuffer(page)).data;
+
+  for (let row = 0; row < 31; row++) {
+    await expect(rowLit(pixels, row)).toBeFalsy();
+    await expect(rowLit(pixels, 255 - row)).toBeFalsy();
+  }
+
+  for (let row = 33; row < 223; row++) {
+    await expect(rowLit(pixels, row)).toBeTruthy();
+  }
+
+  // Running a new script turns the camera off, so it no longer draws into
+  // its buffer after it is cleared
+  await run(page, `clear(); present('camera'); await sleep(500);`);
+
+  pixels = png.decode(await imageBuffer(page)).data;
+
+  await expect(
+    pixels.some((value, i) => i % 4 != 3 && value != 0)
+  ).toBeFalsy();
+});
+
+test('offline-audio', async ({ page }) => {
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 30; i++) {
+        await frame();
+      }
+      frequency();
+      render();
+    `
+  );
+
+  const pixels = png.decode(await imageBuffer(page)).data;
+
+  await expect(
+    pixels.some((value, i) => i % 4 != 3 && value != 0)
+  ).toBeTruthy();
+});
+
+test('offline-audio-reset', async ({ page }) => {
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 30; i++) {
+        await frame();
+      }
+    `
+  );
+
+  // Running a new script stops the track, so frequency data comes from the
+  // silent analyser again
+  await run(page, 'await frame(); frequency(); render();');
+
+  const pixels = png.decode(await imageBuffer(page)).data;
+
+  await expect(
+    pixels.some((value, i) => i % 4 != 3 && value != 0)
+  ).toBeFalsy();
+});
+
+test('offline-audio-zero', async ({ page }) => {
+  try {
+    await run(page, 'offlineAudio(0);');
+  } catch {}
+  await expect(await page.locator('samp > *').first()).toHaveText(
+    'Offline audio frame rate must be greater than zero'
+  );
+});
+
+// Whether each row of the 256 by 256 canvas, from top to bottom, has any
+// lit pixels
+async function litRows(page) {
+  const pixels = png.decode(await imageBuffer(page)).data;
+
+  return Array.from({ length: 256 }, (_, row) =>
+    pixels
+      .subarray(row * 256 * 4, (row + 1) * 256 * 4)
+      .some((value, i) => i % 4 != 3 && value != 0)
+  );
+}
+
+test('spectrogram', async ({ page }) => {
+  // Each frame adds a row of the tone at the top of the spectrogram, pushing
+  // older, silent rows down
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 30; i++) {
+        await frame();
+      }
+      clear();
+      spectrogram();
+      render();
+    `
+  );
+
+  let rows = await litRows(page);
+  const before = rows.indexOf(false);
+  await expect(before).toBeGreaterThanOrEqual(20);
+  await expect(before).toBeLessThanOrEqual(40);
+  await expect(rows.slice(before).some((lit) => lit)).toBeFalsy();
+
+  // The one second tone lasts long enough for a second script to scroll the
+  // spectrogram further
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 55; i++) {
+        await frame();
+      }
+      clear();
+      spectrogram();
+      render();
+    `
+  );
+
+  rows = await litRows(page);
+  await expect(rows.indexOf(false)).toBeGreaterThanOrEqual(before + 15);
+});
+
+test('analyser', async ({ page }) => {
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      audioBins(true);
+      analyser(256, 0);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 5; i++) {
+        await frame();
+      }
+      let summary = audioSummary();
+      assert(summary.frequency.length == 128, 'frequency');
+      assert(summary.timeDomain.length == 256, 'timeDomain');
+    `
+  );
+});
+
+test('audio-channel', async ({ page }) => {
+  const left = wav(
+    1,
+    (time) => Math.sin(2 * Math.PI * 1000 * time),
+    () => 0
+  );
+
+  for (const [channel, lit] of [
+    ['right', false],
+    ['left', true],
+  ]) {
+    await run(
+      page,
+      `
+        offlineAudio(60);
+        await audio('${left}');
+        play();
+        for (let i = 0; i < 30; i++) {
+          await frame();
+        }
+        clear();
+        audioChannel('${channel}');
+        frequency();
+        render();
+      `
+    );
+
+    const pixels = png.decode(await imageBuffer(page)).data;
+
+    const columns = Array.from({ length: 256 }, (_, x) =>
+      [0, 1, 2].some((channel) => pixels[x * 4 + channel] != 0)
+    );
+
+    // Frequency increases from left to right, and the field is the same on
+    // every row
+    for (let row = 1; row < 256; row++) {
+      await expect(
+        Buffer.compare(
+          pixels.subarray(0, 256 * 4),
+          pixels.subarray(row * 256 * 4, (row + 1) * 256 * 4)
+        )
+      ).toBe(0);
+    }
+
+    // The 1 kHz tone falls about 1 / 24 of the way across, and nothing above
+    // half of the Nyquist frequency is lit
+    await expect(columns.slice(8, 16).some((lit) => lit)).toBe(lit);
+    await expect(columns.slice(128).some((lit) => lit)).toBeFalsy();
+  }
+});
+
+test('audio-summary', async ({ page }) => {
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      audioBins(true);
+      await audio('${sineWav(1000)}');
+      play();
+      for (let i = 0; i < 30; i++) {
+        await frame();
+      }
+      let summary = audioSummary();
+      assert(summary.rms > 0.5 && summary.rms < 0.9, 'rms');
+      assert(summary.peak > 0.9, 'peak');
+      assert(summary.mid > summary.bass, 'bass');
+      assert(summary.mid > summary.treble, 'treble');
+      assert(Math.abs(summary.centroid - 1000) < 100, 'centroid');
+      assert(summary.frequency.length > 0, 'frequency');
+      assert(summary.timeDomain.length > 0, 'timeDomain');
+    `
+  );
+});
+
+test('beat', async ({ page }) => {
+  const clicks = wav(
+    4,
+    (time) =>
+      Math.exp(-(time % 0.5) * 40) * Math.sin(2 * Math.PI * 2000 * time)
+  );
+
+  await run(
+    page,
+    `
+      offlineAudio(60);
+      await audio('${clicks}');
+      play();
+      for (let i = 0; i < 200; i++) {
+        await frame();
+      }
+      assert(Math.abs(bpm() - 120) < 5, 'bpm: ' + bpm());
+      await beat();
+      assert(beatPhase() < 0.1, 'phase: ' + beatPhase());
+    `
+  );
+});
+
+// Return the delay of each frame of a GIF, in hundredths of a second
+function gifDelays(gif) {
+  const delays = [];
+  let i = 13;
+
+  if (gif[10] & 0x80) {
+    i += 3 * 2 ** ((gif[10] & 0x07) + 1);
+  }
+
+  const skipSubBlocks = () => {
+    while (gif[i] != 0) {
+      i += gif[i] + 1;
+    }
+    i += 1;
+  };
+
+  while (gif[i] != 0x3b) {
+    if (gif[i] == 0x21) {
+      if (gif[i + 1] == 0xf9) {
+        delays.push(gif.readUInt16LE(i + 4));
+      }
+      i += 2;
+      skipSubBlocks();
+    } else if (gif[i] == 0x2c) {
+      const packed = gif[i + 9];
+      i += 10;
+      if (packed & 0x80) {
+        i += 3 * 2 ** ((packed & 0x07) + 1);
+      }
+      i += 1;
+      skipSubBlocks();
+    } else {
+      throw `Unexpected GIF block: ${gif[i]}`;
+    }
+  }
+
+  return delays;
+}
+
+// Return the delay of each frame of an APNG, as a fraction of a second
+function apngDelays(apng) {
+  const delays = [];
+  let i = 8;
+
+  while (i < apng.length) {
+    const length = apng.readUInt32BE(i);
+    const type = apng.toString('ascii', i + 4, i + 8);
+    if (type == 'fcTL') {
+      delays.push([apng.readUInt16BE(i + 28), apng.readUInt16BE(i + 30)]);
+    }
+    i += length + 12;
+  }
+
+  return delays;
+}
+
+// Return the names and contents of the files in a zip archive of stored
+// files
+function zipEntries(zip) {
+  const entries = [];
+  let i = 0;
+
+  while (zip.readUInt32LE(i) == 0x04034b50) {
+    const size = zip.readUInt32LE(i + 18);
+    const nameLength = zip.readUInt16LE(i + 26);
+    const extraLength = zip.readUInt16LE(i + 28);
+    const start = i + 30 + nameLength + extraLength;
+    entries.push([
+      zip.toString('utf8', i + 30, i + 30 + nameLength),
+      zip.subarray(start, start + size),
+    ]);
+    i = start + size;
+  }
+
+  return entries;
+}
+
+async function capture(page, format) {
+  const [download] = await Promise.all([
+    page.waitForEvent('download'),
+    run(
+      page,
+      `
+        capture(2, 10, '${format}');
+        x();
+        await render();
+        await render();
+        await frame();
+      `
+    ),
+  ]);
+
+  return [
+    download.suggestedFilename(),
+    await fs.promises.readFile(await download.path()),
+  ];
+}
+
+test('capture-gif', async ({ page }) => {
+  const [filename, gif] = await capture(page, 'gif');
+  await expect(filename).toBe('degenerate.gif');
+  await expect(gif.toString('ascii', 0, 6)).toBe('GIF89a');
+  await expect(gifDelays(gif)).toEqual([10, 10]);
+});
+
+test('capture-apng', async ({ page }) => {
+  const [filename, apng] = await capture(page, 'apng');
+  await expect(filename).toBe('degenerate.png');
+  await expect(apngDelays(apng)).toEqual([
+    [1, 10],
+    [1, 10],
+  ]);
+  await expect(png.decode(apng).width).toBe(256);
+});
+
+test('capture-zip', async ({ page }) => {
+  const [filename, zip] = await capture(page, 'zip');
+  await expect(filename).toBe('degenerate.zip');
+
+  const entries = zipEntries(zip);
+  await expect(entries.map(([name]) => name)).toEqual([
+    'degenerate-00000.png',
+    'degenerate-00001.png',
+  ]);
+
+  for (const [, image] of entries) {
+    await expect(png.decode(image).width).toBe(256);
+  }
+});
+
+test('capture-zero', async ({ page }) => {
+  for (const [frames, fps, error] of [
+    [0, 10, 'Capture frame count must be greater than zero'],
+    [2, 0, 'Capture frame rate must be greater than zero'],
+  ]) {
+    try {
+      await run(page, `capture(${frames}, ${fps});`);
+    } catch {}
+    await expect(await page.locator('samp > *').first()).toHaveText(error);
+  }
+});
+
+async function download(page, script) {
+  const [download] = await Promise.all([
+    page.waitForEvent('download'),
+    run(page, script),
+  ]);
+
+  return png.decode(await fs.promises.readFile(await download.path()));
+}
+
+test('save-tiled', async ({ page }) => {
+  const script = `
+    recordTiled();
+    rotate(0.01);
+    x();
+    render();
+    sampling('bilinear');
+    scale(0.95);
+    circle();
+    render();
+  `;
+
+  const saved = await download(page, `${script}\nsave();`);
+  await expect(saved.width).toBe(256);
+
+  await page.goto(`http://localhost:${process.env.PORT}`);
+  await page.waitForSelector('html.ready');
+
+  const tiled = await download(page, `${script}\nsaveTiled(256, 256, 96);`);
+  await expect(tiled.width).toBe(256);
+  await expect(tiled.height).toBe(256);
+
+  await expect(Buffer.compare(saved.data, tiled.data)).toBe(0);
+});
+
+test('save-tiled-errors', async ({ page }) => {
+  for (const [script, error] of [
+    [
+      'x(); render(); saveTiled(256, 256);',
+      '`recordTiled()` must be called before rendering filters to be saved with `saveTiled()`',
+    ],
+    [
+      "recordTiled(); wrap('repeat'); scale(2); x(); render(); saveTiled(256, 256);",
+      'Filters that wrap pixels across the edge of the image cannot be saved with tiles',
+    ],
+  ]) {
+    try {
+      await run(page, script);
+    } catch {}
+    await expect(await page.locator('samp > *').first()).toHaveText(error);
+  }
+});
+
 test('delta', async ({ page }) => {
   await run(
     page,
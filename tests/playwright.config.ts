@@ -7,7 +7,18 @@ const config: PlaywrightTestConfig = {
   fullyParallel: false,
   globalSetup: require.resolve('./global-setup'),
   projects: [
-    { name: 'chromium', use: devices['Desktop Chrome'] },
+    {
+      name: 'chromium',
+      use: {
+        ...devices['Desktop Chrome'],
+        launchOptions: {
+          args: [
+            '--use-fake-device-for-media-stream',
+            '--use-fake-ui-for-media-stream',
+          ],
+        },
+      },
+    },
     { name: 'webkit', use: devices['Desktop Webkit'] },
   ],
   reporter: [['html', { open: 'never' }]],
@@ -29,6 +29,7 @@ let vec4 = glMatrix.vec4;
 // ```
 function all() {
   filter.field = 'All';
+  return filter.field;
 }
 
 // Set the alpha blending factor. `alpha` will be used to blend the
@@ -43,6 +44,49 @@ function alpha(alpha) {
   filter.alpha = alpha;
 }
 
+// Configure audio analysis. `fftSize` is the number of samples analyzed each
+// frame, which must be a power of two from 32 to 32768, and defaults to 2048.
+// Audio fields have half as many frequency bins as samples, so larger sizes
+// give finer frequency resolution, but react more slowly. `smoothing`, from 0
+// to 1, defaults to 0.5, and averages frequency data over time, with higher
+// values giving smoother, slower changes.
+//
+// ```
+// analyser(256, 0.8);
+// while (true) {
+//   clear();
+//   equalizer();
+//   render();
+//   await frame();
+// }
+// ```
+function analyser(fftSize, smoothing) {
+  self.postMessage(
+    JSON.stringify({
+      analyser: {
+        fftSize: fftSize ?? 2048,
+        smoothing: smoothing ?? 0.5,
+      },
+    })
+  );
+}
+
+// Enable or disable aspect-aware rendering, which defaults to disabled. When
+// disabled, buffers are square, with sides as long as the longer side of the
+// canvas, and the canvas shows a center crop. When enabled, buffers are the
+// same size as the canvas, and the shorter axis spans less than [-1, 1], so
+// that fields and transforms stay aspect-correct. Enabling or disabling
+// aspect-aware rendering clears all buffers.
+//
+// ```
+// aspect(true);
+// circle(0.5);
+// render();
+// ```
+function aspect(enabled) {
+  self.postMessage(JSON.stringify({ aspect: enabled ?? true }));
+}
+
 // Assert that `condition` is true, otherwise throw `message`.
 function assert(condition, message) {
   if (!condition) {
@@ -50,6 +94,210 @@ function assert(condition, message) {
   }
 }
 
+// Load an audio track, which is played through the speakers and analyzed by
+// the audio fields, like `wave` and `frequency`. `source` may be a URL or a
+// `Blob`, and may be in any format that the browser can decode. The track
+// starts paused at the beginning. The track is stopped, and offline mode is
+// disabled, when a new script runs. See `play`, `pause`, `seek`, and
+// `offlineAudio`.
+//
+// ```
+// await audio('https://example.com/track.mp3');
+// play();
+// while (true) {
+//   wave();
+//   render();
+//   await frame();
+// }
+// ```
+async function audio(source) {
+  self.postMessage(JSON.stringify({ audio: await base64(source) }));
+}
+
+// Include the raw analyser bins in the summary returned by `audioSummary`.
+// When enabled, the summary has `frequency` and `timeDomain` arrays, which are
+// otherwise `null`. Bins are disabled by default, since copying them every
+// frame is not free.
+//
+// ```
+// audioBins(true);
+// await frame();
+// let bins = audioSummary().frequency;
+// ```
+function audioBins(enabled) {
+  self.postMessage(JSON.stringify({ audioBins: enabled ?? true }));
+}
+
+// Set the audio channel that audio fields react to. `channel` may be `mix`,
+// `left`, or `right`, and defaults to `mix`, a mono mix of both channels.
+// Mono audio is analyzed the same on both channels.
+//
+// ```
+// while (true) {
+//   clear();
+//   audioChannel('left');
+//   transformField(wave(), 0, [1, 1], [0, 0.5]);
+//   render();
+//   audioChannel('right');
+//   transformField(wave(), 0, [1, 1], [0, -0.5]);
+//   render();
+//   await frame();
+// }
+// ```
+function audioChannel(channel) {
+  filter.audioChannel = channel ?? 'mix';
+}
+
+// Return a summary of the audio analyzed on the last frame, with `rms` and
+// `peak`, the root-mean-square and peak amplitude of the waveform, `bass`,
+// `mid`, and `treble`, the energy of the spectrum below 250 Hz, between 250 Hz
+// and 4 kHz, and above 4 kHz, and `centroid`, the spectral centroid in Hz.
+// Energies and the centroid are computed from linear magnitudes, so they do
+// not depend on `decibelRange`. The summary is updated every frame.
+//
+// ```
+// await audio('https://example.com/track.mp3');
+// play();
+// while (true) {
+//   reboot();
+//   let { bass, treble } = audioSummary();
+//   circle();
+//   scale(0.5 + bass);
+//   rotate(treble * TAU);
+//   render();
+//   await frame();
+// }
+// ```
+function audioSummary() {
+  return lastAudio;
+}
+
+// Wait until the next beat. Beats are detected in the audio as sudden
+// increases in spectral energy, and then kept on a steady clock at the
+// estimated tempo, so that beats continue through quiet passages. See `bpm`,
+// `beatPhase`, and `tapTempo`.
+//
+// ```
+// await audio('https://example.com/track.mp3');
+// play();
+// let rotation = 0;
+// while (true) {
+//   await beat();
+//   reboot();
+//   rotation += 0.125 * TAU;
+//   rotate(rotation);
+//   x();
+//   render();
+// }
+// ```
+async function beat() {
+  await new Promise((resolve, reject) => {
+    beatCallbacks.push(resolve);
+  });
+}
+
+// Return how far the last frame was between the previous beat and the next
+// one, from 0 at a beat to almost 1 just before the next.
+//
+// ```
+// while (true) {
+//   reboot();
+//   circle(1 - beatPhase());
+//   render();
+//   await frame();
+// }
+// ```
+function beatPhase() {
+  return lastTempo.phase;
+}
+
+// Set the blend mode, which determines how the transformed color is combined
+// with the original color before alpha blending. `mode` may be `normal`,
+// `add`, `multiply`, `screen`, `overlay`, `difference`, `lighten`, `darken`,
+// or `xor`, and defaults to `normal`, which uses the transformed color as-is.
+//
+// ```
+// check();
+// render();
+// reset();
+// blend('difference');
+// rotateColor('red', 0.25 * TAU);
+// circle(0.75);
+// render();
+// ```
+function blend(mode) {
+  filter.blend = mode ?? 'normal';
+}
+
+// Return the estimated tempo in beats per minute, or 0 if no tempo has been
+// detected yet. Tempos are reported between 80 and 160 beats per minute, so
+// slower or faster music is reported at double or half speed.
+//
+// ```
+// while (true) {
+//   reboot();
+//   rotate(elapsed() / 60000 * bpm() * TAU);
+//   x();
+//   render();
+//   await frame();
+// }
+// ```
+function bpm() {
+  return lastTempo.bpm;
+}
+
+// Start drawing video from the camera into `buffer`, which defaults to
+// `camera`, every frame. The browser will ask for permission to use the
+// camera. `placement` may be `fit` or `fill`, and defaults to `fill`. See
+// `loadImage` for details. Filters can then sample the camera with `source`,
+// or use it as a mask with `mask`. The camera keeps running through
+// `clear()`, and is turned off when a new script runs.
+//
+// ```
+// camera();
+// source('camera');
+// while (true) {
+//   render();
+//   await frame();
+// }
+// ```
+function camera(buffer, placement) {
+  self.postMessage(
+    JSON.stringify({
+      camera: {
+        buffer: buffer ?? 'camera',
+        placement: placement ?? 'fill',
+      },
+    })
+  );
+}
+
+// Capture the next `frames` frames and download them as an animation that
+// plays back at `fps` frames per second. One frame is captured for every
+// frame that the browser displays. `format` may be `gif` for an animated GIF,
+// `apng` for an animated PNG, or `zip` for a zip archive of numbered PNGs, and
+// defaults to `gif`.
+//
+// ```
+// capture(60, 30, 'apng');
+// scale(0.99);
+// circle();
+// for (let i = 0; i < 60; i++) {
+//   await render();
+// }
+// ```
+function capture(frames, fps, format) {
+  self.postMessage(
+    JSON.stringify({
+      capture: {
+        frames,
+        fps: fps ?? 30,
+        format: format ?? 'gif',
+      },
+    })
+  );
+}
+
 // A checkerboard pattern.
 //
 // ```
@@ -58,6 +306,7 @@ function assert(condition, message) {
 // ```
 function check() {
   filter.field = 'Check';
+  return filter.field;
 }
 
 // Create a new checkbox widget with the label `name`, and return true if it is
@@ -87,14 +336,17 @@ function checkbox(name) {
   return !!widgets['checkbox-' + name];
 }
 
-// A circle.
+// A circle with radius `radius`, which defaults to 1.0.
 //
 // ```
 // circle();
 // render();
+// circle(0.5);
+// render();
 // ```
-function circle() {
-  filter.field = 'Circle';
+function circle(radius) {
+  filter.field = { Circle: { radius: radius ?? 1.0 } };
+  return filter.field;
 }
 
 // Clear the canvas.
@@ -108,14 +360,64 @@ function clear() {
   self.postMessage(JSON.stringify('clear'));
 }
 
-// A cross field.
+// Set the color space that the color transformation is applied in. `space`
+// may be `rgb`, `hsv`, `hsl`, `oklab`, or `oklch`, and defaults to `rgb`.
+// Colors are converted into the color space, transformed, and converted back.
+//
+// Vectors in each color space are:
+//
+// - `rgb`: red, green, and blue, mapped from [0, 1] to [-1, 1]
+// - `hsv`: hue in turns, saturation, and value
+// - `hsl`: hue in turns, saturation, and lightness
+// - `oklab`: lightness, green-red, and blue-yellow
+// - `oklch`: lightness, chroma, and hue in turns
+//
+// `rotateHue`, `scaleChroma`, and `scaleLightness` set the color transform
+// appropriately for the current color space, so call `colorSpace` first.
+//
+// ```
+// colorSpace('oklch');
+// rotateHue(TAU / 3);
+// render();
+// ```
+function colorSpace(space) {
+  filter.colorSpace = space ?? 'rgb';
+}
+
+// A cross field. Each arm of the cross extends `size` from the center, and is
+// `thickness` thick. `size` defaults to 1.0, and `thickness` defaults to 0.25.
 //
 // ```
 // cross();
 // render();
+// cross(0.5, 0.1);
+// render();
 // ```
-function cross() {
-  filter.field = 'Cross';
+function cross(size, thickness) {
+  filter.field = {
+    Cross: { size: size ?? 1.0, thickness: thickness ?? 0.25 },
+  };
+  return filter.field;
+}
+
+// A custom field, defined by GLSL source `source`, which must define a
+// function `float f(vec2 p)` returning the signed distance from `p` to the
+// field's boundary. Pixels where the distance is negative are inside of the
+// field. `p` ranges from -1.0 to 1.0 on both axes. Sources are compiled the
+// first time they are used, and compiler errors are reported with line
+// numbers.
+//
+// ```
+// customField(`
+//   float f(vec2 p) {
+//     return abs(p.x) + abs(p.y) - 0.5;
+//   }
+// `);
+// render();
+// ```
+function customField(source) {
+  filter.field = { Custom: source };
+  return filter.field;
 }
 
 // Set the decibel range for normalization of raw frequency data into values
@@ -179,6 +481,20 @@ function delta() {
   return lastDelta;
 }
 
+// Set the name of the buffer that renders are blended onto. Buffers are
+// created the first time they are used, and the canvas shows the `default`
+// buffer unless another is selected with `present`.
+//
+// ```
+// destination('background');
+// x();
+// render();
+// present('background');
+// ```
+function destination(destination) {
+  filter.destination = destination;
+}
+
 // Return the number of milliseconds that have elapsed since the page was loaded.
 //
 // ```
@@ -205,6 +521,21 @@ function elapsed() {
 // ```
 function equalizer() {
   filter.field = 'Equalizer';
+  return filter.field;
+}
+
+// Set the feather width. When `width` is greater than zero, alpha ramps
+// smoothly across field boundaries over `width`, measured in the same units as
+// field distances, where the canvas is 2.0 across, instead of cutting off
+// sharply. `width` defaults to 0.0.
+//
+// ```
+// feather(0.1);
+// circle(0.5);
+// render();
+// ```
+function feather(width) {
+  filter.feather = width ?? 0.0;
 }
 
 // Returns a promise that resolves when the browser is ready to display a new
@@ -227,9 +558,25 @@ async function frame() {
   });
 }
 
-// A frequency field.
-function frequency() {
-  filter.field = 'Frequency';
+// A frequency field, covering pixels where the normalized audio frequency
+// data is greater than `threshold`, which defaults to 0.125.
+function frequency(threshold) {
+  filter.field = { Frequency: { threshold: threshold ?? 0.125 } };
+  return filter.field;
+}
+
+// Set the glow falloff. When `falloff` is greater than zero, pixels outside of
+// the field are blended with alpha that decays exponentially with their
+// distance from the field, falling to 1/e at `falloff`. `falloff` defaults to
+// 0.0, which disables glow.
+//
+// ```
+// glow(0.1);
+// x(1.0, 0.05);
+// render();
+// ```
+function glow(falloff) {
+  filter.glow = falloff ?? 0.0;
 }
 
 // Set the color transformation to the identity transformation. The identity
@@ -259,6 +606,19 @@ function coordinates(coordinates) {
   filter.coordinates = coordinates;
 }
 
+// Set the field to the intersection of fields `a` and `b`, covering pixels
+// covered by both. Field functions return the field they set, so they can be
+// used as arguments.
+//
+// ```
+// intersect(circle(), square(1.5));
+// render();
+// ```
+function intersect(a, b) {
+  filter.field = { Intersect: [a, b] };
+  return filter.field;
+}
+
 // Set the color transformation to inversion.
 //
 // ```
@@ -278,6 +638,48 @@ function invert() {
   mat4.fromScaling(filter.colorTransform, vec3.fromValues(-1, -1, -1));
 }
 
+// Load an image into `buffer`, which defaults to `default`. `image` may be a
+// URL or a `Blob`, and may be a PNG, JPEG, GIF, or any other format that the
+// `image` crate can decode. `placement` may be `fit`, which scales the image
+// to fit within the canvas, or `fill`, which scales the image to cover the
+// canvas, and defaults to `fit`. The image is centered, and areas not covered
+// by the image are black. The buffer is reset to the image whenever it is
+// recreated, for example by `clear()` or when the canvas is resized, until a
+// new script runs.
+//
+// ```
+// await loadImage('https://example.com/photo.jpg', 'fill');
+// circle(0.5);
+// render();
+// ```
+async function loadImage(image, placement, buffer) {
+  self.postMessage(
+    JSON.stringify({
+      loadImage: {
+        image: await base64(image),
+        placement: placement ?? 'fit',
+        buffer: buffer ?? 'default',
+      },
+    })
+  );
+}
+
+// Set the name of a buffer to use as a mask, or `null` for no mask. The
+// alpha of each pixel is multiplied by the brightness of the mask at that
+// pixel.
+//
+// ```
+// destination('mask');
+// circle(0.5);
+// render();
+// reset();
+// mask('mask');
+// render();
+// ```
+function mask(mask) {
+  filter.mask = mask;
+}
+
 // Field that covers pixels where the pixel's index mod `divisor` is equal to `remainder`.
 //
 // ```
@@ -286,6 +688,32 @@ function invert() {
 // ```
 function mod(divisor, remainder) {
   filter.field = { Mod: { divisor, remainder } };
+  return filter.field;
+}
+
+// Enable offline audio, which is useful for audio-reactive renders and tests
+// that must be the same on every run. In offline mode, the track loaded with
+// `audio` is not played through the speakers. Instead, while playing, its
+// position advances by `1 / fps` seconds every frame, regardless of how long
+// frames take to render, and the audio fields see the track at that position.
+// `fps` must be greater than zero, and defaults to 60. Pass `null` to disable
+// offline mode.
+//
+// ```
+// offlineAudio(30);
+// await audio('https://example.com/track.mp3');
+// play();
+// capture(300, 30);
+// while (true) {
+//   frequency();
+//   render();
+//   await frame();
+// }
+// ```
+function offlineAudio(fps) {
+  self.postMessage(
+    JSON.stringify({ offlineAudio: fps === undefined ? 60 : fps })
+  );
 }
 
 // Set the oscillator gain. The oscillator produces a sine wave tone, useful
@@ -320,6 +748,60 @@ function oscillatorFrequency(oscillatorFrequency) {
   self.postMessage(JSON.stringify({ oscillatorFrequency }));
 }
 
+// Pause the track loaded with `audio`.
+//
+// ```
+// play();
+// await sleep(1000);
+// pause();
+// ```
+function pause() {
+  self.postMessage(JSON.stringify('pause'));
+}
+
+// Play the track loaded with `audio` from its current position.
+//
+// ```
+// await audio('https://example.com/track.mp3');
+// play();
+// ```
+function play() {
+  self.postMessage(JSON.stringify('play'));
+}
+
+// Set the precision of render buffers. `precision` may be `rgba8`, for eight
+// bits per channel, `rgba16f`, for half precision floating point, or
+// `rgba32f`, for single precision floating point, and defaults to `rgba8`.
+// Floating point buffers avoid the banding and drift that builds up over many
+// feedback passes, and are saved as 16-bit PNGs. `rgba32f` falls back to
+// `rgba16f` if the browser doesn't support single precision buffers. Changing
+// the precision clears all buffers.
+//
+// ```
+// precision('rgba16f');
+// identity();
+// rotateColor('red', 0.01);
+// for (let i = 0; i < 100; i++) {
+//   render();
+// }
+// ```
+function precision(precision) {
+  self.postMessage(JSON.stringify({ precision: precision ?? 'rgba8' }));
+}
+
+// Show the buffer named `buffer` on the canvas. Subsequent renders continue
+// to show `buffer` until `present` is called again.
+//
+// ```
+// destination('background');
+// check();
+// render();
+// present('background');
+// ```
+function present(buffer) {
+  self.postMessage(JSON.stringify({ present: buffer }));
+}
+
 // Create a new radio button widget with the label `name` and options `options`,
 // and return the selected option. `options` must be a list of strings. Calls with
 // same `name` will all refer to the same radio button widget, making it safe to
@@ -372,6 +854,36 @@ function record() {
   self.postMessage(JSON.stringify('record'));
 }
 
+// Start recording a session. Every message sent to the renderer after this
+// call is recorded along with the frame it was received on. The session can
+// be downloaded with `saveSession()`, and played back without running a
+// script using the `Replay` button. Sessions are useful for reproducing
+// scripts that render differently on different machines.
+//
+// ```
+// recordSession();
+// x();
+// render();
+// saveSession();
+// ```
+function recordSession() {
+  self.postMessage(JSON.stringify('recordSession'));
+}
+
+// Start recording rendered filters, so that they can be saved with
+// `saveTiled()`. Filters are recorded until the next `clear()`, which empties
+// the recording but keeps recording.
+//
+// ```
+// recordTiled();
+// x();
+// render();
+// saveTiled(16384, 16384);
+// ```
+function recordTiled() {
+  self.postMessage(JSON.stringify('recordTiled'));
+}
+
 // Send the current filter to the main thread to be rendered. Like `frame()`,
 // returns a promise that will resolve when the browser is ready to display a
 // new frame. Use `await frame();` when you want to render multiple times before
@@ -401,20 +913,22 @@ function reset() {
   filter = new Filter();
 }
 
-// Set resolution to a fixed value. Normally, the resolution increases and
-// decreases automatically as the window is resized. This is usually what you
-// want, but it is convenient to override it if you want to render at a fixed
-// size, for example for saving high-resolution images:
+// Set resolution to a fixed `width` by `height`, with `height` defaulting to
+// `width`. Normally, the resolution increases and decreases automatically as
+// the window is resized. This is usually what you want, but it is convenient
+// to override it if you want to render at a fixed size, for example for saving
+// high-resolution images:
 //
 // ```
-// resolution(4096);
+// resolution(3840, 2160);
 // x();
 // render();
 // save();
 // ```
-function resolution(resolution) {
-  if (Number.isInteger(resolution)) {
-    self.postMessage(JSON.stringify({ resolution }));
+function resolution(width, height) {
+  height ??= width;
+  if (Number.isInteger(width) && Number.isInteger(height)) {
+    self.postMessage(JSON.stringify({ resolution: { width, height } }));
   }
 }
 
@@ -444,6 +958,36 @@ function rotateColor(axis, radians) {
   }
 }
 
+// Set the color transformation to a hue rotation by `radians` in the current
+// color space. In `rgb`, this is a rotation about the gray axis. In the other
+// color spaces, lightness is unchanged.
+//
+// ```
+// check();
+// rotateColor('green', 0.5 * TAU);
+// render();
+// colorSpace('hsv');
+// rotateHue(TAU / 3);
+// render();
+// ```
+function rotateHue(radians) {
+  switch (filter.colorSpace) {
+    case 'rgb':
+      mat4.fromRotation(filter.colorTransform, radians, [1, 1, 1]);
+      break;
+    case 'hsv':
+    case 'hsl':
+      mat4.fromTranslation(filter.colorTransform, [radians / TAU, 0, 0]);
+      break;
+    case 'oklab':
+      mat4.fromXRotation(filter.colorTransform, radians);
+      break;
+    case 'oklch':
+      mat4.fromTranslation(filter.colorTransform, [0, 0, radians / TAU]);
+      break;
+  }
+}
+
 // Set coordinate transform to a rotation.
 //
 // ```
@@ -464,6 +1008,23 @@ function rotate(rotation) {
 // ```
 function rows(on, off) {
   filter.field = { Rows: { on, off } };
+  return filter.field;
+}
+
+// Set the sampling mode used when reading from the source buffer. `mode` may
+// be `nearest`, `bilinear`, or `bicubic`, and defaults to `nearest`. Bilinear
+// and bicubic sampling smooth over the blockiness and aliasing that nearest
+// sampling produces when scaling.
+//
+// ```
+// circle();
+// render();
+// sampling('bicubic');
+// scale(0.1);
+// render();
+// ```
+function sampling(mode) {
+  filter.sampling = mode ?? 'nearest';
 }
 
 // Save the current canvas as a PNG.
@@ -478,6 +1039,47 @@ function save() {
   self.postMessage(JSON.stringify('save'));
 }
 
+// Download the session started by `recordSession()` as a JSON file.
+//
+// ```
+// recordSession();
+// x();
+// render();
+// saveSession();
+// ```
+function saveSession() {
+  self.postMessage(JSON.stringify('saveSession'));
+}
+
+// Save a `width` by `height` PNG, which may be larger than the GPU's maximum
+// texture size. Everything rendered since `recordTiled()` or the last
+// `clear()` is replayed at the new size, one tile at a time, and the tiles are
+// stitched together. Tiles are at most `tileSize` pixels on a side, defaulting
+// to 4096, and overlap by as far as the recorded filters move pixels, so
+// filters that move pixels a long way, like large rotations, need large tiles.
+// Filters that wrap pixels across the edge of the image can't be saved with
+// tiles. Fields that use audio see the audio they were rendered with, except
+// for `spectrogram`, which sees the current spectrogram, and the camera's
+// buffer shows its current frame.
+//
+// ```
+// recordTiled();
+// x();
+// render();
+// saveTiled(16384, 16384);
+// ```
+function saveTiled(width, height, tileSize) {
+  self.postMessage(
+    JSON.stringify({
+      saveTiled: {
+        width,
+        height,
+        tileSize: tileSize ?? null,
+      },
+    })
+  );
+}
+
 // Set the current scale to `scale`. The scale factor is applied to sample coordinates before
 // looking up the pixel under those coordinates.
 //
@@ -491,6 +1093,83 @@ function scale(scale) {
   transform(0, [scale, scale], [0.0, 0.0]);
 }
 
+// Set the color transformation to scale chroma, or saturation, by `factor` in
+// the current color space. In `rgb`, this scales distance from the gray axis.
+//
+// ```
+// colorSpace('oklch');
+// scaleChroma(0.5);
+// render();
+// ```
+function scaleChroma(factor) {
+  switch (filter.colorSpace) {
+    case 'rgb': {
+      let gray = (1 - factor) / 3;
+      let other = factor + gray;
+      mat4.set(
+        filter.colorTransform,
+        ...[other, gray, gray, 0],
+        ...[gray, other, gray, 0],
+        ...[gray, gray, other, 0],
+        ...[0, 0, 0, 1]
+      );
+      break;
+    }
+    case 'hsv':
+    case 'hsl':
+    case 'oklch':
+      mat4.fromScaling(filter.colorTransform, [1, factor, 1]);
+      break;
+    case 'oklab':
+      mat4.fromScaling(filter.colorTransform, [1, factor, factor]);
+      break;
+  }
+}
+
+// Set the color transformation to scale lightness by `factor` in the current
+// color space. In `rgb`, this scales distance from middle gray along the gray
+// axis.
+//
+// ```
+// colorSpace('oklab');
+// scaleLightness(0.5);
+// render();
+// ```
+function scaleLightness(factor) {
+  switch (filter.colorSpace) {
+    case 'rgb': {
+      let gray = (factor - 1) / 3;
+      let other = 1 + gray;
+      mat4.set(
+        filter.colorTransform,
+        ...[other, gray, gray, 0],
+        ...[gray, other, gray, 0],
+        ...[gray, gray, other, 0],
+        ...[0, 0, 0, 1]
+      );
+      break;
+    }
+    case 'hsv':
+    case 'hsl':
+      mat4.fromScaling(filter.colorTransform, [1, 1, factor]);
+      break;
+    case 'oklab':
+    case 'oklch':
+      mat4.fromScaling(filter.colorTransform, [factor, 1, 1]);
+      break;
+  }
+}
+
+// Move the track loaded with `audio` to `time` seconds from the beginning.
+//
+// ```
+// seek(30);
+// play();
+// ```
+function seek(time) {
+  self.postMessage(JSON.stringify({ seek: time }));
+}
+
 // Return a promise that resolves after `ms` milliseconds.
 //
 // ```
@@ -535,19 +1214,109 @@ function slider(name, min, max, step, initial) {
   return widgets['slider-' + name] ?? initial;
 }
 
-// A square field.
+// Set the field to the smooth union of fields `a` and `b`, blending the
+// seam between them over a distance of `k`.
+//
+// ```
+// smoothUnion(circle(0.5), square(1.5, 0.25), 0.25);
+// render();
+// ```
+function smoothUnion(a, b, k) {
+  filter.field = { SmoothUnion: { a, b, k } };
+  return filter.field;
+}
+
+// Set the name of the buffer that renders sample from.
+//
+// ```
+// destination('background');
+// check();
+// render();
+// source('background');
+// destination('default');
+// rotateColor('green', 0.5 * TAU);
+// render();
+// ```
+function source(source) {
+  filter.source = source;
+}
+
+// A scrolling spectrogram field, covering pixels where the normalized audio
+// frequency data is greater than `threshold`, which defaults to 0.125. Like
+// `frequency`, frequency increases from left to right, and the last 256
+// frames of frequency data run from bottom to top, oldest to newest.
+//
+// ```
+// await audio('https://example.com/track.mp3');
+// play();
+// while (true) {
+//   clear();
+//   spectrogram();
+//   render();
+//   await frame();
+// }
+// ```
+function spectrogram(threshold) {
+  filter.field = { Spectrogram: { threshold: threshold ?? 0.125 } };
+  return filter.field;
+}
+
+// A rectangle field, `width` wide and `height` high. `width` defaults to 1.0,
+// and `height` defaults to `width`.
 //
 // ```
 // square();
 // render();
+// square(1.5, 0.5);
+// render();
+// ```
+function square(width, height) {
+  filter.field = {
+    Square: { width: width ?? 1.0, height: height ?? width ?? 1.0 },
+  };
+  return filter.field;
+}
+
+// Set the field to field `a` with field `b` subtracted, covering pixels
+// covered by `a` but not by `b`.
+//
+// ```
+// subtract(circle(), circle(0.5));
+// render();
+// ```
+function subtract(a, b) {
+  filter.field = { Subtract: [a, b] };
+  return filter.field;
+}
+
+// Create a tap tempo widget with the label `name`. Tapping its `Tap` button on
+// the beat overrides the detected tempo and phase, which is useful when the
+// music is hard to follow, and clicking `Auto` returns to detection.
+//
+// ```
+// tapTempo('tempo');
+// while (true) {
+//   await beat();
+//   rotate(0.125 * TAU);
+//   x();
+//   render();
+// }
 // ```
-function square() {
-  filter.field = 'Square';
+function tapTempo(name) {
+  self.postMessage(
+    JSON.stringify({
+      widget: {
+        name,
+        widget: 'tapTempo',
+      },
+    })
+  );
 }
 
 // A field that covers pixels where the audio time domain data is large.
 function timeDomain() {
   filter.field = 'TimeDomain';
+  return filter.field;
 }
 
 // Execute the filter `times` times.
@@ -563,6 +1332,7 @@ function times(times) {
 // ```
 function top() {
   filter.field = 'Top';
+  return filter.field;
 }
 
 // Set the coordinate transform using `rotation`, `scale`, and `translation`.
@@ -585,7 +1355,37 @@ function transform(rotation, scale, translation) {
   );
 }
 
-// A Waveform field.
+// Set the field to `field`, with its coordinates transformed by `rotation`,
+// `scale`, and `translation`, like `transform`. Omitted arguments default to
+// no rotation, unit scale, and no translation.
+//
+// ```
+// union(x(), transformField(x(), TAU / 8, [2.0, 2.0]));
+// render();
+// ```
+function transformField(field, rotation, scale, translation) {
+  let matrix = mat3.create();
+  mat3.rotate(matrix, matrix, rotation ?? 0.0);
+  mat3.scale(matrix, matrix, scale ?? [1.0, 1.0]);
+  mat3.translate(matrix, matrix, translation ?? [0.0, 0.0]);
+  filter.field = { Transform: { field, transform: matrix } };
+  return filter.field;
+}
+
+// Set the field to the union of fields `a` and `b`, covering pixels covered
+// by either.
+//
+// ```
+// union(circle(0.5), cross());
+// render();
+// ```
+function union(a, b) {
+  filter.field = { Union: [a, b] };
+  return filter.field;
+}
+
+// A Waveform field, covering pixels within `thickness` of the audio
+// waveform. `thickness` defaults to 0.1.
 //
 // ```
 // record();
@@ -595,30 +1395,51 @@ function transform(rotation, scale, translation) {
 //   await render();
 // }
 // ```
-function wave() {
-  filter.field = 'Wave';
+function wave(thickness) {
+  filter.field = { Wave: { thickness: thickness ?? 0.1 } };
+  return filter.field;
 }
 
-// Set wrap. When `wrap` is `true`, out of bounds samples will be wrapped back within bounds.
+// Set the wrap mode, which determines what happens to out of bounds samples.
+// `mode` may be:
+//
+// - `repeat`: Samples wrap around to the opposite edge.
+// - `mirror`: Samples are reflected back across the edge.
+// - `clamp`: Samples are clamped to the nearest edge.
+// - `defaultColor`: Samples use the default color.
+//
+// `true` is equivalent to `repeat`, `false` is equivalent to `defaultColor`,
+// and `mode` defaults to `repeat`.
 //
 // ```
 // x();
-// wrap();
+// wrap('mirror');
 // scale(0.1);
 // render();
 // ```
-function wrap(warp) {
-  filter.wrap = warp;
+function wrap(mode) {
+  if (mode === undefined || mode === true) {
+    filter.wrap = 'repeat';
+  } else if (mode === false) {
+    filter.wrap = 'defaultColor';
+  } else {
+    filter.wrap = mode;
+  }
 }
 
-// An X field.
+// An X field. `size` controls the length of the X's arms, and `radius` is
+// half the width of its strokes. `size` defaults to 2.0, and `radius` defaults
+// to 0.25.
 //
 // ```
 // x();
 // render();
+// x(1.0, 0.1);
+// render();
 // ```
-function x() {
-  filter.field = 'X';
+function x(size, radius) {
+  filter.field = { X: { size: size ?? 2.0, radius: radius ?? 0.25 } };
+  return filter.field;
 }
 
 // The ratio of a circle's circumference to its diameter. Useful for expressing
@@ -650,6 +1471,9 @@ class Rng {
 class Filter {
   constructor() {
     this.alpha = 1.0;
+    this.audioChannel = 'mix';
+    this.blend = 'normal';
+    this.colorSpace = 'rgb';
     this.colorTransform = mat4.fromScaling(
       mat4.create(),
       vec3.fromValues(-1, -1, -1)
@@ -657,15 +1481,54 @@ class Filter {
     this.positionTransform = mat3.create();
     this.coordinates = false;
     this.defaultColor = [0.0, 0.0, 0.0];
+    this.destination = 'default';
+    this.feather = 0.0;
     this.field = 'All';
+    this.glow = 0.0;
+    this.mask = null;
+    this.source = 'default';
     this.times = 1;
-    this.wrap = false;
+    this.sampling = 'nearest';
+    this.wrap = 'defaultColor';
   }
 }
 
+// Fetch `source`, which may be a URL or a `Blob`, and encode its contents as
+// base64, so that it can be sent to the renderer.
+async function base64(source) {
+  if (typeof source === 'string') {
+    const response = await fetch(source);
+    if (!response.ok) {
+      throw `Failed to fetch ${source}: ${response.status} ${response.statusText}`;
+    }
+    source = await response.blob();
+  }
+
+  const bytes = new Uint8Array(await source.arrayBuffer());
+
+  let binary = '';
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    binary += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+
+  return btoa(binary);
+}
+
+let beatCallbacks = [];
 let frameCallbacks = [];
+let lastAudio = {
+  bass: 0,
+  centroid: 0,
+  frequency: null,
+  mid: 0,
+  peak: 0,
+  rms: 0,
+  timeDomain: null,
+  treble: 0,
+};
 let lastDelta = 0;
 let lastFrame = 0;
+let lastTempo = { beat: false, bpm: 0, phase: 0 };
 let rng = new Rng();
 let start = Date.now();
 let filter = new Filter();
@@ -676,6 +1539,14 @@ self.addEventListener('message', async function (event) {
   const message = JSON.parse(event.data);
   switch (message.tag) {
     case 'frame':
+      lastAudio = message.content.audio;
+      lastTempo = message.content.tempo;
+      if (lastTempo.beat) {
+        for (let callback of beatCallbacks) {
+          callback();
+        }
+        beatCallbacks = [];
+      }
       for (let callback of frameCallbacks) {
         callback();
       }
@@ -687,6 +1558,7 @@ self.addEventListener('message', async function (event) {
       lastFrame = now;
       break;
     case 'script':
+      beatCallbacks = [];
       frameCallbacks = [];
       try {
         await new AsyncFunction(message.content)();